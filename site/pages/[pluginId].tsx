@@ -1,11 +1,14 @@
 import { Plugin } from "@/types";
 import { cn } from "@/util/cn";
-import { getConfig } from "@/util/config";
-import { readdir, readFile } from "fs/promises";
+import {
+  getConfig,
+  getManifest,
+  readScreenHtml,
+  shownCapture,
+} from "@/util/config";
 import { GetStaticPropsContext } from "next";
 import Link from "next/link";
 import { useRouter } from "next/router";
-import path from "path";
 import React from "react";
 
 type Screen = {
@@ -23,27 +26,30 @@ type PluginPageProps = {
   plugin: Plugin;
 };
 
-const PLUGINS_OUT_DIR = process.env.PLUGINS_OUT_DIR!;
-
 export async function getStaticProps(context: GetStaticPropsContext) {
   const { pluginId } = context.params as { pluginId: string };
-  const colorschemeOutputDir = path.join(PLUGINS_OUT_DIR, pluginId);
-  const colorschemeDirNames = await readdir(colorschemeOutputDir);
+  const manifest = await getManifest();
+  const manifestPlugin = manifest.plugins.find(
+    (plugin) => plugin.id === pluginId
+  );
   const colorschemeShows: ColorschemeShow[] = [];
-  for (const colorschemeDirName of colorschemeDirNames) {
-    const colorschemeDir = path.join(colorschemeOutputDir, colorschemeDirName);
-    const screenFilenames = await readdir(colorschemeDir);
+  for (const colorscheme of manifestPlugin?.colorschemes ?? []) {
+    const capture = shownCapture(colorscheme.captures);
+    if (!capture) {
+      continue;
+    }
     const screens: Screen[] = [];
-    for (const screenFilename of screenFilenames) {
-      const screenFile = path.join(colorschemeDir, screenFilename);
-      const screenHtml = await readFile(screenFile, "utf8");
+    for (const screen of capture.screens) {
+      if (!screen.files.html) {
+        continue;
+      }
       screens.push({
-        name: screenFilename.replace(/\.html$/, ""),
-        html: screenHtml,
+        name: screen.title,
+        html: await readScreenHtml(capture, screen),
       });
     }
     colorschemeShows.push({
-      name: colorschemeDirName,
+      name: colorscheme.name,
       screens,
     });
   }
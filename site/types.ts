@@ -1,7 +1,10 @@
 export type Config = {
   plugins: Plugin[];
+  backgrounds?: Background[];
 };
 
+export type Background = "dark" | "light";
+
 export type Plugin = {
   id: string;
   url: string;
@@ -15,3 +18,54 @@ export type ColorschemeWithPreview = {
   plugin: Plugin;
   previewHtml: string;
 };
+
+/** Contents of `manifest.json` in `PLUGINS_OUT_DIR`. */
+export type Manifest = {
+  version: 1;
+  /** Start of the run, in RFC 3339. */
+  capturedAt: string;
+  nvimVersion: string;
+  size: { columns: number; lines: number };
+  plugins: ManifestPlugin[];
+};
+
+export type ManifestPlugin = {
+  id: string;
+  name: string;
+  url: string;
+  colorschemes: ManifestColorscheme[];
+};
+
+export type ManifestColorscheme = {
+  name: string;
+  /**
+   * Start of the captures, in RFC 3339. Colorschemes skipped as unchanged keep the time of the run
+   * that captured them.
+   */
+  capturedAt: string;
+  fingerprint?: string;
+  /** Path of `variants.json`, relative to `PLUGINS_OUT_DIR`, if the config has backgrounds. */
+  variants?: string;
+  captures: ManifestCapture[];
+};
+
+export type ManifestCapture = {
+  background: Background | null;
+  status: "ok" | "failed";
+  error?: string;
+  /** Path of the stylesheet of the HTML snapshots, relative to `PLUGINS_OUT_DIR`. */
+  stylesheet?: string;
+  /** Path of `highlights.json`, relative to `PLUGINS_OUT_DIR`. */
+  highlights?: string;
+  /** Path of `contrast.json`, relative to `PLUGINS_OUT_DIR`. */
+  contrast?: string;
+  /** Screens in the order the scenarios take them. */
+  screens: ManifestScreen[];
+};
+
+export type ManifestScreen = {
+  title: string;
+  kind: "snapshot" | "animation" | "recording";
+  /** Paths relative to `PLUGINS_OUT_DIR`, by format. */
+  files: Record<string, string>;
+};
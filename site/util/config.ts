@@ -1,10 +1,18 @@
-import { ColorschemeWithPreview, Config, Plugin } from "@/types";
+import {
+  ColorschemeWithPreview,
+  Config,
+  Manifest,
+  ManifestCapture,
+  ManifestScreen,
+  Plugin,
+} from "@/types";
 import { readFile } from "fs/promises";
 import path from "path";
 
 const PLUGINS_OUT_DIR = process.env.PLUGINS_OUT_DIR!;
 const CONFIG_PATH = process.env.CONFIG_PATH!;
 let _config: Config | undefined;
+let _manifest: Manifest | undefined;
 
 export async function getConfig(): Promise<Config> {
   if (!_config) {
@@ -13,27 +21,67 @@ export async function getConfig(): Promise<Config> {
   return _config!;
 }
 
+export async function getManifest(): Promise<Manifest> {
+  if (!_manifest) {
+    _manifest = JSON.parse(
+      await readFile(path.join(PLUGINS_OUT_DIR, "manifest.json"), "utf8")
+    );
+  }
+  return _manifest!;
+}
+
 export async function getPlugins(): Promise<Plugin[]> {
   const config = await getConfig();
   return config.plugins;
 }
 
+/** The capture shown on the site: the first background's, when it succeeded. */
+export function shownCapture(
+  captures: ManifestCapture[]
+): ManifestCapture | undefined {
+  const capture = captures[0];
+  return capture?.status === "ok" ? capture : undefined;
+}
+
+/** HTML of `screen`, with the stylesheet of `capture` if it has one. */
+export async function readScreenHtml(
+  capture: ManifestCapture,
+  screen: ManifestScreen
+): Promise<string> {
+  const screenHtml = await readFile(
+    path.join(PLUGINS_OUT_DIR, screen.files.html),
+    "utf8"
+  );
+  if (!capture.stylesheet) {
+    return screenHtml;
+  }
+  const paletteCss = await readFile(
+    path.join(PLUGINS_OUT_DIR, capture.stylesheet),
+    "utf8"
+  );
+  return `<style>${paletteCss}</style>${screenHtml}`;
+}
+
 export async function getColorschemesWithPreview(): Promise<
   ColorschemeWithPreview[]
 > {
-  const plugins = await getPlugins();
+  const config = await getConfig();
+  const manifest = await getManifest();
   const colorschemesWithPreview: ColorschemeWithPreview[] = [];
-  for (const plugin of plugins) {
-    const pluginDir = path.join(PLUGINS_OUT_DIR, plugin.id);
-    for (const colorscheme of plugin.colorschemes) {
-      const previewHtml = await readFile(
-        path.join(pluginDir, colorscheme, "Rust.html"),
-        "utf8"
-      );
+  for (const manifestPlugin of manifest.plugins) {
+    const plugin = config.plugins.find(
+      (plugin) => plugin.id === manifestPlugin.id
+    )!;
+    for (const colorscheme of manifestPlugin.colorschemes) {
+      const capture = shownCapture(colorscheme.captures);
+      const screen = capture?.screens.find((screen) => screen.files.html);
+      if (!capture || !screen) {
+        continue;
+      }
       colorschemesWithPreview.push({
-        name: colorscheme,
+        name: colorscheme.name,
         plugin,
-        previewHtml,
+        previewHtml: await readScreenHtml(capture, screen),
       });
     }
   }
@@ -1,14 +1,36 @@
-use bpaf::{construct, short, Parser};
+use bpaf::{construct, long, short, Parser};
+
+use crate::{config::Size, export::Format};
 
 pub(crate) struct Args {
     pub(crate) out_dir: String,
     pub(crate) config: String,
+    pub(crate) scenarios: Option<String>,
+    pub(crate) scenario: Vec<String>,
+    pub(crate) formats: Vec<Format>,
+    pub(crate) size: Option<Size>,
+    pub(crate) force: bool,
 }
 
 pub(crate) fn parse() -> Args {
     let out_dir = out_dir();
     let config = config();
-    construct!(Args { out_dir, config }).to_options().run()
+    let scenarios = scenarios();
+    let scenario = scenario();
+    let formats = formats();
+    let size = size();
+    let force = force();
+    construct!(Args {
+        out_dir,
+        config,
+        scenarios,
+        scenario,
+        formats,
+        size,
+        force
+    })
+    .to_options()
+    .run()
 }
 
 pub(crate) fn out_dir() -> impl Parser<String> {
@@ -24,3 +46,51 @@ pub(crate) fn config() -> impl Parser<String> {
         .env("CONFIG_PATH")
         .argument("CONFIG_PATH")
 }
+
+pub(crate) fn scenarios() -> impl Parser<Option<String>> {
+    short('s')
+        .long("scenarios")
+        .env("SCENARIOS_PATH")
+        .help("TOML or JSON file with the scenarios to run, overrides `scenarios` in the config")
+        .argument("SCENARIOS_PATH")
+        .optional()
+}
+
+pub(crate) fn scenario() -> impl Parser<Vec<String>> {
+    long("scenario")
+        .help("Name of a scenario to run, can be repeated. Runs every scenario by default")
+        .argument("NAME")
+        .many()
+}
+
+pub(crate) fn formats() -> impl Parser<Vec<Format>> {
+    short('f')
+        .long("format")
+        .env("FORMATS")
+        .help(
+            "Comma separated formats snapshots are exported to: html, svg, png, json, ansi, gif. \
+             Only html by default",
+        )
+        .argument::<String>("FORMATS")
+        .parse(|formats| {
+            formats
+                .split(',')
+                .map(|format| format.trim().parse())
+                .collect::<Result<Vec<Format>, String>>()
+        })
+        .fallback(vec![Format::Html])
+}
+
+pub(crate) fn size() -> impl Parser<Option<Size>> {
+    long("size")
+        .env("TERM_SIZE")
+        .help("Size of the terminal as COLUMNSxLINES, overrides `size` in the config")
+        .argument::<Size>("SIZE")
+        .optional()
+}
+
+pub(crate) fn force() -> impl Parser<bool> {
+    long("force")
+        .help("Captures every colorscheme, even the ones unchanged since the previous run")
+        .switch()
+}
@@ -0,0 +1,163 @@
+use std::{
+    io::{BufWriter, Write},
+    path::Path,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::config::Size;
+
+/// Output of nvim closer in time than this is written as a single event.
+const EVENT_WINDOW_SECS: f64 = 0.01;
+
+/// Output of nvim recorded between a `start_recording` and a `stop_recording` step, written as
+/// an asciicast v2 file.
+pub(crate) struct Recording {
+    title: String,
+    size: Size,
+    start: Instant,
+    timestamp: u64,
+    /// Output with the seconds since `start` it was read at.
+    events: Vec<(f64, Vec<u8>)>,
+}
+
+#[derive(Serialize)]
+struct Header<'a> {
+    version: u32,
+    width: usize,
+    height: usize,
+    timestamp: u64,
+    title: &'a str,
+}
+
+impl Recording {
+    /// Starts a recording whose first frame is `screen`, since players start from an empty
+    /// terminal.
+    pub(crate) fn new(title: String, screen: String, size: Size) -> Recording {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        Recording {
+            title,
+            size,
+            start: Instant::now(),
+            timestamp,
+            events: vec![(0.0, screen.into_bytes())],
+        }
+    }
+
+    pub(crate) fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub(crate) fn push(&mut self, byte: u8) {
+        let time = self.start.elapsed().as_secs_f64();
+        match self.events.last_mut() {
+            Some((last_time, bytes)) if time - *last_time < EVENT_WINDOW_SECS => bytes.push(byte),
+            _ => self.events.push((time, vec![byte])),
+        }
+    }
+
+    /// Writes `{title}.cast` to `out_dir`.
+    pub(crate) fn export(&self, out_dir: &Path) {
+        std::fs::create_dir_all(out_dir).unwrap();
+        let file = std::fs::File::create(out_dir.join(format!("{}.cast", self.title))).unwrap();
+        let mut file = BufWriter::new(file);
+        let header = Header {
+            version: 2,
+            width: self.size.columns,
+            height: self.size.lines,
+            timestamp: self.timestamp,
+            title: &self.title,
+        };
+        writeln!(file, "{}", serde_json::to_string(&header).unwrap()).unwrap();
+        for (time, data) in self.text_events() {
+            let event = (format!("{time:.6}").parse::<f64>().unwrap(), "o", data);
+            writeln!(file, "{}", serde_json::to_string(&event).unwrap()).unwrap();
+        }
+    }
+
+    /// Events as text. Events must be valid UTF-8, so chars split between events are moved to the
+    /// next one.
+    fn text_events(&self) -> Vec<(f64, String)> {
+        let mut events = Vec::new();
+        let mut pending = Vec::new();
+        for (time, bytes) in &self.events {
+            pending.extend_from_slice(bytes);
+            let complete_len = match std::str::from_utf8(&pending) {
+                Err(err) if err.error_len().is_none() => err.valid_up_to(),
+                _ => pending.len(),
+            };
+            let data = String::from_utf8_lossy(&pending[..complete_len]).into_owned();
+            pending.drain(..complete_len);
+            if !data.is_empty() {
+                events.push((*time, data));
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Recording;
+    use crate::config::Size;
+
+    fn recording(events: &[(f64, &[u8])]) -> Recording {
+        let mut recording = Recording::new("test".to_string(), String::new(), Size::default());
+        recording.events = events
+            .iter()
+            .map(|(time, bytes)| (*time, bytes.to_vec()))
+            .collect();
+        recording
+    }
+
+    fn text_events(events: &[(f64, &[u8])]) -> Vec<(f64, String)> {
+        recording(events).text_events()
+    }
+
+    #[test]
+    fn ascii_events_are_kept() {
+        assert_eq!(
+            text_events(&[(0.0, b"ab"), (0.5, b"c")]),
+            [(0.0, "ab".to_string()), (0.5, "c".to_string())]
+        );
+    }
+
+    #[test]
+    fn split_chars_move_to_the_next_event() {
+        // `漢` is encoded as e6 bc a2.
+        assert_eq!(
+            text_events(&[(0.0, b"a\xe6"), (0.5, b"\xbc\xa2b")]),
+            [(0.0, "a".to_string()), (0.5, "漢b".to_string())]
+        );
+    }
+
+    #[test]
+    fn events_with_only_part_of_a_char_are_skipped() {
+        assert_eq!(
+            text_events(&[(0.0, b"\xe6"), (0.1, b"\xbc"), (0.2, b"\xa2")]),
+            [(0.2, "漢".to_string())]
+        );
+    }
+
+    #[test]
+    fn invalid_bytes_are_replaced() {
+        assert_eq!(
+            text_events(&[(0.0, b"a\xffb"), (0.5, b"c")]),
+            [(0.0, "a\u{fffd}b".to_string()), (0.5, "c".to_string())]
+        );
+    }
+
+    #[test]
+    fn pushed_bytes_are_grouped_into_events() {
+        let mut recording = recording(&[]);
+        for &byte in "é".as_bytes() {
+            recording.push(byte);
+        }
+        let events = recording.text_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].1, "é");
+    }
+}
@@ -1,199 +1,801 @@
+use std::path::Path;
+
 use alacritty_terminal::{
     ansi::{Color, NamedColor},
     term::color::Rgb,
 };
+use eyre::{bail, eyre, WrapErr};
+
+use crate::{
+    config::{PaletteConfig, TransparentBackground},
+    nvim::TerminalColors,
+};
+
+/// CSS color of the default background when the palette leaves it transparent.
+pub(crate) const TRANSPARENT: &str = "transparent";
+
+/// Factor alacritty scales the foreground of dim text by.
+const DIM_FACTOR: f32 = 0.66;
+
+/// Levels of the red, green and blue components in the xterm 6x6x6 color cube.
+const CUBE_LEVELS: [u8; 6] = [0x00, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
+
+/// Tomorrow Night, which the terminal used before its colors could be configured.
+const DEFAULT_BASE: [u32; 16] = [
+    0x1d1f21, 0xcc6666, 0xb5bd68, 0xf0c674, 0x81a2be, 0xb294bb, 0x8abeb7, 0xc5c8c6, 0x666666,
+    0xd54e53, 0xb9ca4a, 0xe7c547, 0x7aa6da, 0xc397d8, 0x70c0b1, 0xeaeaea,
+];
+const DEFAULT_FOREGROUND: u32 = 0xc5c8c6;
+const DEFAULT_BACKGROUND: u32 = 0x1d1f21;
+/// Colors of Tomorrow, the light variant of Tomorrow Night.
+const LIGHT_FOREGROUND: u32 = 0x4d4d4c;
+const LIGHT_BACKGROUND: u32 = 0xffffff;
+
+/// Colors of the terminal, for the cells nvim doesn't give an RGB color.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Palette {
+    /// The 16 base colors followed by the xterm color cube and gray ramp.
+    indexed: [Rgb; 256],
+    foreground: Rgb,
+    background: Rgb,
+    /// The cursor takes the colors of the cell under it if unset.
+    cursor: Option<Rgb>,
+    /// Whether cells with the default background are left transparent.
+    transparent: bool,
+}
 
-pub(crate) fn named_to_rgb(named: NamedColor) -> Rgb {
-    match named {
-        NamedColor::Black => Rgb {
-            r: 0x1d,
-            g: 0x1f,
-            b: 0x21,
-        },
-        NamedColor::Red => Rgb {
-            r: 0xcc,
-            g: 0x66,
-            b: 0x66,
-        },
-        NamedColor::Green => Rgb {
-            r: 0xb5,
-            g: 0xbd,
-            b: 0x68,
-        },
-        NamedColor::Yellow => Rgb {
-            r: 0xf0,
-            g: 0xc6,
-            b: 0x74,
-        },
-        NamedColor::Blue => Rgb {
-            r: 0x81,
-            g: 0xa2,
-            b: 0xbe,
-        },
-        NamedColor::Magenta => Rgb {
-            r: 0xb2,
-            g: 0x94,
-            b: 0xbb,
-        },
-        NamedColor::Cyan => Rgb {
-            r: 0x8a,
-            g: 0xbe,
-            b: 0xb7,
-        },
-        NamedColor::White => Rgb {
-            r: 0xc5,
-            g: 0xc8,
-            b: 0xc6,
-        },
-        NamedColor::BrightBlack => Rgb {
-            r: 0x66,
-            g: 0x66,
-            b: 0x66,
-        },
-        NamedColor::BrightRed => Rgb {
-            r: 0xd5,
-            g: 0x4e,
-            b: 0x53,
-        },
-        NamedColor::BrightGreen => Rgb {
-            r: 0xb9,
-            g: 0xca,
-            b: 0x4a,
-        },
-        NamedColor::BrightYellow => Rgb {
-            r: 0xe7,
-            g: 0xc5,
-            b: 0x47,
-        },
-        NamedColor::BrightBlue => Rgb {
-            r: 0x7a,
-            g: 0xa6,
-            b: 0xda,
-        },
-        NamedColor::BrightMagenta => Rgb {
-            r: 0xc3,
-            g: 0x97,
-            b: 0xd8,
-        },
-        NamedColor::BrightCyan => Rgb {
-            r: 0x70,
-            g: 0xc0,
-            b: 0xb1,
-        },
-        NamedColor::BrightWhite => Rgb {
-            r: 0xea,
-            g: 0xea,
-            b: 0xea,
-        },
-        NamedColor::Foreground => Rgb {
-            r: 0xc5,
-            g: 0xc8,
-            b: 0xc6,
-        },
-        NamedColor::Background => Rgb {
-            r: 0x1d,
-            g: 0x1f,
-            b: 0x21,
-        },
-        NamedColor::Cursor => Rgb {
-            r: 0x3d,
-            g: 0x3f,
-            b: 0x41,
-        },
-        NamedColor::DimBlack => Rgb {
-            r: 0x13,
-            g: 0x14,
-            b: 0x15,
-        },
-        NamedColor::DimRed => Rgb {
-            r: 0x86,
-            g: 0x43,
-            b: 0x43,
-        },
-        NamedColor::DimGreen => Rgb {
-            r: 0x77,
-            g: 0x7c,
-            b: 0x44,
-        },
-        NamedColor::DimYellow => Rgb {
-            r: 0x9e,
-            g: 0x82,
-            b: 0x4c,
-        },
-        NamedColor::DimBlue => Rgb {
-            r: 0x55,
-            g: 0x6a,
-            b: 0x7d,
-        },
-        NamedColor::DimMagenta => Rgb {
-            r: 0x75,
-            g: 0x61,
-            b: 0x7b,
-        },
-        NamedColor::DimCyan => Rgb {
-            r: 0x5b,
-            g: 0x7d,
-            b: 0x78,
-        },
-        NamedColor::DimWhite => Rgb {
-            r: 0x82,
-            g: 0x84,
-            b: 0x82,
-        },
-        NamedColor::BrightForeground => Rgb {
-            r: 0xc5,
-            g: 0xc8,
-            b: 0xc6,
-        },
-        NamedColor::DimForeground => Rgb {
-            r: 0xc5,
-            g: 0xc8,
-            b: 0xc6,
-        },
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::new(
+            DEFAULT_BASE.map(hex_rgb),
+            hex_rgb(DEFAULT_FOREGROUND),
+            hex_rgb(DEFAULT_BACKGROUND),
+            None,
+        )
     }
 }
 
-pub(crate) fn to_string(color: Color) -> String {
-    match color {
-        Color::Spec(rgb) => rgb.to_string(),
-        Color::Named(name) => named_to_rgb(name).to_string(),
-        Color::Indexed(index) => {
-            let ansi_colors = vec![
-                NamedColor::Black,
-                NamedColor::Red,
-                NamedColor::Green,
-                NamedColor::Yellow,
-                NamedColor::Blue,
-                NamedColor::Magenta,
-                NamedColor::Cyan,
-                NamedColor::White,
-                NamedColor::BrightBlack,
-                NamedColor::BrightRed,
-                NamedColor::BrightGreen,
-                NamedColor::BrightYellow,
-                NamedColor::BrightBlue,
-                NamedColor::BrightMagenta,
-                NamedColor::BrightCyan,
-                NamedColor::BrightWhite,
-            ]
-            .into_iter()
-            .map(|named| named_to_rgb(named).to_string())
-            .collect::<Vec<_>>();
-            match index {
-                0..=15 => ansi_colors[index as usize].clone(),
-                16..=231 => {
-                    let index = index - 16;
-                    let r = ((index / (6 * 6)) % 6) * 40 + 55;
-                    let g = ((index / 6) % 6) * 40 + 45;
-                    let b = (index % 6) * 40 + 45;
-                    format!("#{r:X}{g:X}{b:X}")
-                }
-                232..=255 => {
-                    let gray_level = index - 233;
-                    let gray_value = gray_level * 10 + 8;
-                    format!("#{0:X}{0:X}{0:X}", gray_value)
+impl Palette {
+    pub(crate) fn new(
+        base: [Rgb; 16],
+        foreground: Rgb,
+        background: Rgb,
+        cursor: Option<Rgb>,
+    ) -> Palette {
+        let mut indexed = [Rgb::default(); 256];
+        indexed[..16].copy_from_slice(&base);
+        for (i, rgb) in indexed.iter_mut().enumerate().skip(16) {
+            *rgb = xterm_rgb(i as u8);
+        }
+        Palette {
+            indexed,
+            foreground,
+            background,
+            cursor,
+            transparent: false,
+        }
+    }
+
+    /// Default palette with the colors set by the palette file and the config on top.
+    pub(crate) fn load(config: &PaletteConfig) -> eyre::Result<Palette> {
+        let mut palette = Palette::default();
+        if let Some(path) = &config.file {
+            let text = std::fs::read_to_string(path)
+                .wrap_err_with(|| format!("failed to read palette {}", path.display()))?;
+            let result = if is_yaml(path) {
+                palette.apply_base16(&text)
+            } else {
+                palette.apply_xresources(&text)
+            };
+            result.wrap_err_with(|| format!("invalid palette {}", path.display()))?;
+        }
+        if let Some(colors) = config.colors {
+            palette.indexed[..16].copy_from_slice(&colors);
+        }
+        palette.foreground = config.foreground.unwrap_or(palette.foreground);
+        palette.background = config.background.unwrap_or(palette.background);
+        palette.cursor = config.cursor.or(palette.cursor);
+        Ok(palette)
+    }
+
+    /// Palette with the colors a colorscheme gives the terminal on top, those it leaves unset, or
+    /// sets to something other than `#rrggbb`, keep theirs. The default background is chosen by
+    /// `transparent_background` if Normal has no background.
+    pub(crate) fn with_terminal_colors(
+        &self,
+        colors: &TerminalColors,
+        transparent_background: TransparentBackground,
+    ) -> Palette {
+        let parse = |color: &Option<String>| -> Option<Rgb> {
+            let color = color.as_deref()?;
+            let rgb = color.parse().ok();
+            if rgb.is_none() {
+                warn!(color, "ignored terminal color that isn't #rrggbb");
+            }
+            rgb
+        };
+        let mut palette = self.clone();
+        for (rgb, color) in palette.indexed.iter_mut().zip(&colors.colors) {
+            *rgb = parse(color).unwrap_or(*rgb);
+        }
+        let (fg, bg) = (parse(&colors.normal.fg), parse(&colors.normal.bg));
+        if bg.is_none() {
+            match transparent_background {
+                TransparentBackground::Palette => {}
+                TransparentBackground::Background => {
+                    let (foreground, background) = if colors.normal.background == "light" {
+                        (LIGHT_FOREGROUND, LIGHT_BACKGROUND)
+                    } else {
+                        (DEFAULT_FOREGROUND, DEFAULT_BACKGROUND)
+                    };
+                    palette.foreground = hex_rgb(foreground);
+                    palette.background = hex_rgb(background);
                 }
+                TransparentBackground::Transparent => palette.transparent = true,
+                TransparentBackground::Color(color) => palette.background = color,
+            }
+        }
+        palette.foreground = fg.unwrap_or(palette.foreground);
+        palette.background = bg.unwrap_or(palette.background);
+        palette
+    }
+
+    pub(crate) fn rgb(&self, color: Color) -> Rgb {
+        match color {
+            Color::Spec(rgb) => rgb,
+            Color::Named(named) => self.named(named),
+            Color::Indexed(index) => self.indexed[index as usize],
+        }
+    }
+
+    pub(crate) fn named(&self, named: NamedColor) -> Rgb {
+        match named {
+            NamedColor::Foreground | NamedColor::BrightForeground => self.foreground,
+            NamedColor::Background => self.background,
+            NamedColor::Cursor => self.cursor.unwrap_or(self.foreground),
+            NamedColor::DimForeground => self.foreground * DIM_FACTOR,
+            NamedColor::DimBlack
+            | NamedColor::DimRed
+            | NamedColor::DimGreen
+            | NamedColor::DimYellow
+            | NamedColor::DimBlue
+            | NamedColor::DimMagenta
+            | NamedColor::DimCyan
+            | NamedColor::DimWhite => {
+                self.indexed[named as usize - NamedColor::DimBlack as usize] * DIM_FACTOR
+            }
+            // The remaining names are the base colors, numbered like their index.
+            named => self.indexed[named as usize],
+        }
+    }
+
+    /// Color the cursor is drawn with when nvim doesn't set one.
+    pub(crate) fn cursor(&self) -> Option<Rgb> {
+        self.cursor
+    }
+
+    /// Color the space around the grid is filled with, `None` if it's left transparent.
+    pub(crate) fn background(&self) -> Option<Rgb> {
+        (!self.transparent).then_some(self.background)
+    }
+
+    /// Returns `color` as `#rrggbb`.
+    pub(crate) fn to_hex(&self, color: Color) -> String {
+        self.rgb(color).to_string()
+    }
+
+    /// Same as `to_hex` for a background `color`, which is `transparent` if it's the default
+    /// background and the palette leaves it transparent.
+    pub(crate) fn to_bg_hex(&self, color: Color) -> String {
+        if self.transparent && color == Color::Named(NamedColor::Background) {
+            TRANSPARENT.to_string()
+        } else {
+            self.to_hex(color)
+        }
+    }
+
+    /// Returns the color dim text with the foreground `color` is drawn with.
+    pub(crate) fn dim(&self, color: Color) -> String {
+        (self.rgb(color) * DIM_FACTOR).to_string()
+    }
+
+    /// Sets the colors of a base16 scheme, mapped to the terminal like base16-shell does.
+    fn apply_base16(&mut self, text: &str) -> eyre::Result<()> {
+        let mut base = [None; 16];
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim();
+            let Some(digits) = key.strip_prefix("base") else {
+                continue;
+            };
+            let mut digits = digits.chars().map(|digit| digit.to_digit(16));
+            let (Some(Some(0)), Some(Some(i)), None) =
+                (digits.next(), digits.next(), digits.next())
+            else {
+                bail!("unknown base16 color `{key}`");
+            };
+            base[i as usize] = Some(parse_base16_value(value)?);
+        }
+        let missing = (0..16)
+            .filter(|&i| base[i].is_none())
+            .map(|i| format!("base0{i:X}"))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            bail!("missing {}", missing.join(", "));
+        }
+        let base = base.map(Option::unwrap);
+        const TERMINAL_BASES: [usize; 16] = [
+            0x0, 0x8, 0xb, 0xa, 0xd, 0xe, 0xc, 0x5, 0x3, 0x8, 0xb, 0xa, 0xd, 0xe, 0xc, 0x7,
+        ];
+        for (color, i) in self.indexed.iter_mut().zip(TERMINAL_BASES) {
+            *color = base[i];
+        }
+        self.foreground = base[0x5];
+        self.background = base[0x0];
+        self.cursor = Some(base[0x5]);
+        Ok(())
+    }
+
+    /// Sets the colors an Xresources file defines, whatever the class or instance they're for.
+    fn apply_xresources(&mut self, text: &str) -> eyre::Result<()> {
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            // `!` starts comments and `#` preprocessor directives.
+            if line.is_empty() || line.starts_with('!') || line.starts_with('#') {
+                continue;
+            }
+            let Some((resource, value)) = line.split_once(':') else {
+                continue;
+            };
+            let name = resource
+                .rsplit(['.', '*'])
+                .next()
+                .unwrap_or(resource)
+                .trim();
+            let target = match name {
+                "foreground" => &mut self.foreground,
+                "background" => &mut self.background,
+                "cursorColor" => self.cursor.get_or_insert(Rgb::default()),
+                name => match name
+                    .strip_prefix("color")
+                    .and_then(|i| i.parse::<usize>().ok())
+                {
+                    Some(i) if i < 16 => &mut self.indexed[i],
+                    _ => continue,
+                },
+            };
+            let value = value.trim();
+            *target = value
+                .parse()
+                .map_err(|_| eyre!("line {}: invalid color `{value}`", number + 1))?;
+        }
+        Ok(())
+    }
+}
+
+fn is_yaml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "yaml" || extension == "yml")
+}
+
+/// Parses a base16 color, written with or without quotes and `#`.
+fn parse_base16_value(value: &str) -> eyre::Result<Rgb> {
+    let value = value.trim();
+    let value = match value.strip_prefix(['"', '\'']) {
+        Some(quoted) => quoted.split(['"', '\'']).next().unwrap_or_default(),
+        None => value.split_whitespace().next().unwrap_or_default(),
+    };
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    format!("#{hex}")
+        .parse()
+        .map_err(|_| eyre!("invalid color `{value}`"))
+}
+
+/// Color xterm gives indices past the base colors: a 6x6x6 cube then a ramp of 24 grays.
+fn xterm_rgb(index: u8) -> Rgb {
+    match index {
+        0..=15 => unreachable!("base colors are configured"),
+        16..=231 => {
+            let index = index - 16;
+            Rgb {
+                r: CUBE_LEVELS[usize::from(index / 36)],
+                g: CUBE_LEVELS[usize::from(index / 6 % 6)],
+                b: CUBE_LEVELS[usize::from(index % 6)],
             }
         }
+        232..=255 => {
+            let level = 8 + 10 * (index - 232);
+            Rgb {
+                r: level,
+                g: level,
+                b: level,
+            }
+        }
+    }
+}
+
+fn hex_rgb(hex: u32) -> Rgb {
+    Rgb {
+        r: (hex >> 16) as u8,
+        g: (hex >> 8) as u8,
+        b: hex as u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alacritty_terminal::{
+        ansi::{Color, NamedColor},
+        term::color::Rgb,
+    };
+
+    use super::Palette;
+    use crate::{
+        config::{PaletteConfig, TransparentBackground},
+        nvim::{NormalColors, TerminalColors},
+    };
+
+    /// Colors xterm gives indices 16 to 255.
+    const XTERM: [&str; 240] = [
+        "#000000", "#00005f", "#000087", "#0000af", "#0000d7", "#0000ff", "#005f00", "#005f5f",
+        "#005f87", "#005faf", "#005fd7", "#005fff", "#008700", "#00875f", "#008787", "#0087af",
+        "#0087d7", "#0087ff", "#00af00", "#00af5f", "#00af87", "#00afaf", "#00afd7", "#00afff",
+        "#00d700", "#00d75f", "#00d787", "#00d7af", "#00d7d7", "#00d7ff", "#00ff00", "#00ff5f",
+        "#00ff87", "#00ffaf", "#00ffd7", "#00ffff", "#5f0000", "#5f005f", "#5f0087", "#5f00af",
+        "#5f00d7", "#5f00ff", "#5f5f00", "#5f5f5f", "#5f5f87", "#5f5faf", "#5f5fd7", "#5f5fff",
+        "#5f8700", "#5f875f", "#5f8787", "#5f87af", "#5f87d7", "#5f87ff", "#5faf00", "#5faf5f",
+        "#5faf87", "#5fafaf", "#5fafd7", "#5fafff", "#5fd700", "#5fd75f", "#5fd787", "#5fd7af",
+        "#5fd7d7", "#5fd7ff", "#5fff00", "#5fff5f", "#5fff87", "#5fffaf", "#5fffd7", "#5fffff",
+        "#870000", "#87005f", "#870087", "#8700af", "#8700d7", "#8700ff", "#875f00", "#875f5f",
+        "#875f87", "#875faf", "#875fd7", "#875fff", "#878700", "#87875f", "#878787", "#8787af",
+        "#8787d7", "#8787ff", "#87af00", "#87af5f", "#87af87", "#87afaf", "#87afd7", "#87afff",
+        "#87d700", "#87d75f", "#87d787", "#87d7af", "#87d7d7", "#87d7ff", "#87ff00", "#87ff5f",
+        "#87ff87", "#87ffaf", "#87ffd7", "#87ffff", "#af0000", "#af005f", "#af0087", "#af00af",
+        "#af00d7", "#af00ff", "#af5f00", "#af5f5f", "#af5f87", "#af5faf", "#af5fd7", "#af5fff",
+        "#af8700", "#af875f", "#af8787", "#af87af", "#af87d7", "#af87ff", "#afaf00", "#afaf5f",
+        "#afaf87", "#afafaf", "#afafd7", "#afafff", "#afd700", "#afd75f", "#afd787", "#afd7af",
+        "#afd7d7", "#afd7ff", "#afff00", "#afff5f", "#afff87", "#afffaf", "#afffd7", "#afffff",
+        "#d70000", "#d7005f", "#d70087", "#d700af", "#d700d7", "#d700ff", "#d75f00", "#d75f5f",
+        "#d75f87", "#d75faf", "#d75fd7", "#d75fff", "#d78700", "#d7875f", "#d78787", "#d787af",
+        "#d787d7", "#d787ff", "#d7af00", "#d7af5f", "#d7af87", "#d7afaf", "#d7afd7", "#d7afff",
+        "#d7d700", "#d7d75f", "#d7d787", "#d7d7af", "#d7d7d7", "#d7d7ff", "#d7ff00", "#d7ff5f",
+        "#d7ff87", "#d7ffaf", "#d7ffd7", "#d7ffff", "#ff0000", "#ff005f", "#ff0087", "#ff00af",
+        "#ff00d7", "#ff00ff", "#ff5f00", "#ff5f5f", "#ff5f87", "#ff5faf", "#ff5fd7", "#ff5fff",
+        "#ff8700", "#ff875f", "#ff8787", "#ff87af", "#ff87d7", "#ff87ff", "#ffaf00", "#ffaf5f",
+        "#ffaf87", "#ffafaf", "#ffafd7", "#ffafff", "#ffd700", "#ffd75f", "#ffd787", "#ffd7af",
+        "#ffd7d7", "#ffd7ff", "#ffff00", "#ffff5f", "#ffff87", "#ffffaf", "#ffffd7", "#ffffff",
+        "#080808", "#121212", "#1c1c1c", "#262626", "#303030", "#3a3a3a", "#444444", "#4e4e4e",
+        "#585858", "#626262", "#6c6c6c", "#767676", "#808080", "#8a8a8a", "#949494", "#9e9e9e",
+        "#a8a8a8", "#b2b2b2", "#bcbcbc", "#c6c6c6", "#d0d0d0", "#dadada", "#e4e4e4", "#eeeeee",
+    ];
+
+    const BASE_NAMES: [NamedColor; 16] = [
+        NamedColor::Black,
+        NamedColor::Red,
+        NamedColor::Green,
+        NamedColor::Yellow,
+        NamedColor::Blue,
+        NamedColor::Magenta,
+        NamedColor::Cyan,
+        NamedColor::White,
+        NamedColor::BrightBlack,
+        NamedColor::BrightRed,
+        NamedColor::BrightGreen,
+        NamedColor::BrightYellow,
+        NamedColor::BrightBlue,
+        NamedColor::BrightMagenta,
+        NamedColor::BrightCyan,
+        NamedColor::BrightWhite,
+    ];
+
+    const DIM_NAMES: [NamedColor; 8] = [
+        NamedColor::DimBlack,
+        NamedColor::DimRed,
+        NamedColor::DimGreen,
+        NamedColor::DimYellow,
+        NamedColor::DimBlue,
+        NamedColor::DimMagenta,
+        NamedColor::DimCyan,
+        NamedColor::DimWhite,
+    ];
+
+    const BASE16: &str = r#"
+scheme: "Test"
+author: "Someone"
+base00: "000000"
+base01: "010101"
+base02: "020202"
+base03: "030303"
+base04: "040404"
+base05: "050505"
+base06: "060606"
+base07: "070707"
+base08: "080808"
+base09: "090909"
+base0A: "0a0a0a"
+base0B: "0b0b0b"
+base0C: "0c0c0c"
+base0D: "0d0d0d"
+base0E: "0e0e0e"
+base0F: "0f0f0f"
+"#;
+
+    fn rgb(hex: &str) -> Rgb {
+        hex.parse().unwrap()
+    }
+
+    fn gray(value: u8) -> Rgb {
+        Rgb {
+            r: value,
+            g: value,
+            b: value,
+        }
+    }
+
+    /// Palette whose base color `i` is `#iiiiii`.
+    fn numbered() -> Palette {
+        Palette::new(
+            std::array::from_fn(|i| gray(i as u8)),
+            rgb("#aaaaaa"),
+            rgb("#bbbbbb"),
+            Some(rgb("#cccccc")),
+        )
+    }
+
+    /// Loads `config` with `text` written to a palette file with the given extension.
+    fn load(extension: &str, text: &str, mut config: PaletteConfig) -> eyre::Result<Palette> {
+        let path = crate::temp_path("palette").with_extension(extension);
+        std::fs::write(&path, text).unwrap();
+        config.file = Some(path.clone());
+        let palette = Palette::load(&config);
+        std::fs::remove_file(&path).unwrap();
+        palette
+    }
+
+    #[test]
+    fn indices_past_base_colors_follow_xterm() {
+        let palette = Palette::default();
+        for (i, expected) in (16..=255).zip(XTERM) {
+            assert_eq!(palette.to_hex(Color::Indexed(i)), expected, "index {i}");
+        }
+    }
+
+    #[test]
+    fn xterm_colors_ignore_base_colors() {
+        let palette = numbered();
+        for (i, expected) in (16..=255).zip(XTERM) {
+            assert_eq!(palette.to_hex(Color::Indexed(i)), expected, "index {i}");
+        }
+    }
+
+    #[test]
+    fn base_indices_and_names_use_base_colors() {
+        let palette = numbered();
+        for (i, named) in BASE_NAMES.into_iter().enumerate() {
+            assert_eq!(palette.rgb(Color::Indexed(i as u8)), gray(i as u8));
+            assert_eq!(palette.rgb(Color::Named(named)), gray(i as u8));
+        }
+    }
+
+    #[test]
+    fn every_color_is_lowercase_padded_hex() {
+        let palette = numbered();
+        for i in 0..=255 {
+            let hex = palette.to_hex(Color::Indexed(i));
+            assert_eq!(hex.len(), 7, "index {i}: {hex}");
+            assert!(hex.starts_with('#'), "index {i}: {hex}");
+            assert!(
+                hex[1..]
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)),
+                "index {i}: {hex}"
+            );
+        }
+        assert_eq!(palette.to_hex(Color::Indexed(1)), "#010101");
+    }
+
+    #[test]
+    fn spec_colors_are_kept() {
+        let palette = numbered();
+        let color = rgb("#0a0b0c");
+        assert_eq!(palette.rgb(Color::Spec(color)), color);
+        assert_eq!(palette.to_hex(Color::Spec(color)), "#0a0b0c");
+    }
+
+    #[test]
+    fn special_names_use_their_colors() {
+        let palette = numbered();
+        let named = |named| palette.rgb(Color::Named(named));
+        assert_eq!(named(NamedColor::Foreground), rgb("#aaaaaa"));
+        assert_eq!(named(NamedColor::BrightForeground), rgb("#aaaaaa"));
+        assert_eq!(named(NamedColor::DimForeground), rgb("#aaaaaa") * 0.66);
+        assert_eq!(named(NamedColor::Background), rgb("#bbbbbb"));
+        assert_eq!(named(NamedColor::Cursor), rgb("#cccccc"));
+        for (i, dim) in DIM_NAMES.into_iter().enumerate() {
+            assert_eq!(named(dim), gray(i as u8) * 0.66);
+        }
+    }
+
+    #[test]
+    fn cursor_defaults_to_foreground() {
+        let palette = Palette::default();
+        assert_eq!(palette.cursor(), None);
+        assert_eq!(
+            palette.rgb(Color::Named(NamedColor::Cursor)),
+            rgb("#c5c8c6")
+        );
+    }
+
+    #[test]
+    fn default_is_tomorrow_night() {
+        let palette = Palette::default();
+        let hex = |named| palette.to_hex(Color::Named(named));
+        assert_eq!(hex(NamedColor::Black), "#1d1f21");
+        assert_eq!(hex(NamedColor::Red), "#cc6666");
+        assert_eq!(hex(NamedColor::BrightWhite), "#eaeaea");
+        assert_eq!(hex(NamedColor::Foreground), "#c5c8c6");
+        assert_eq!(hex(NamedColor::Background), "#1d1f21");
+        assert_eq!(hex(NamedColor::DimBlack), "#131415");
+    }
+
+    #[test]
+    fn dim_scales_any_color() {
+        let palette = numbered();
+        assert_eq!(palette.dim(Color::Spec(rgb("#646464"))), "#424242");
+        assert_eq!(
+            palette.dim(Color::Indexed(231)),
+            (rgb("#ffffff") * 0.66).to_string()
+        );
+        assert_eq!(
+            palette.dim(Color::Named(NamedColor::Foreground)),
+            (rgb("#aaaaaa") * 0.66).to_string()
+        );
+    }
+
+    #[test]
+    fn empty_config_loads_default() {
+        assert_eq!(
+            Palette::load(&PaletteConfig::default()).unwrap(),
+            Palette::default()
+        );
+    }
+
+    #[test]
+    fn inline_colors_are_used() {
+        let config = PaletteConfig {
+            colors: Some(std::array::from_fn(|i| gray(i as u8))),
+            foreground: Some(rgb("#aaaaaa")),
+            background: Some(rgb("#bbbbbb")),
+            cursor: Some(rgb("#cccccc")),
+            ..PaletteConfig::default()
+        };
+        assert_eq!(Palette::load(&config).unwrap(), numbered());
+    }
+
+    #[test]
+    fn base16_maps_like_base16_shell() {
+        let palette = load("yaml", BASE16, PaletteConfig::default()).unwrap();
+        let expected = [
+            0x0, 0x8, 0xb, 0xa, 0xd, 0xe, 0xc, 0x5, 0x3, 0x8, 0xb, 0xa, 0xd, 0xe, 0xc, 0x7,
+        ];
+        for (i, base) in expected.into_iter().enumerate() {
+            assert_eq!(
+                palette.rgb(Color::Indexed(i as u8)),
+                gray(base),
+                "index {i}"
+            );
+        }
+        assert_eq!(palette.named(NamedColor::Foreground), gray(0x5));
+        assert_eq!(palette.named(NamedColor::Background), gray(0x0));
+        assert_eq!(palette.cursor(), Some(gray(0x5)));
+    }
+
+    #[test]
+    fn base16_accepts_any_quoting() {
+        let text = BASE16
+            .replace("\"000000\"", "'#000000'")
+            .replace("\"050505\"", "050505 # foreground")
+            .replace("base0F", "  base0F");
+        let palette = load("yml", &text, PaletteConfig::default()).unwrap();
+        assert_eq!(
+            palette,
+            load("yaml", BASE16, PaletteConfig::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn base16_needs_every_color() {
+        let text = BASE16.replace("base0A", "other").replace("base03", "other");
+        let err = load("yaml", &text, PaletteConfig::default()).unwrap_err();
+        assert!(
+            format!("{err:?}").contains("missing base03, base0A"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn base16_rejects_unknown_colors() {
+        for key in [
+            "base10", "base17", "base010", "base0FF", "base0G", "base0", "base",
+        ] {
+            let text = format!("{BASE16}{key}: \"000000\"\n");
+            let err = load("yaml", &text, PaletteConfig::default()).unwrap_err();
+            assert!(
+                format!("{err:?}").contains(&format!("unknown base16 color `{key}`")),
+                "{err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn base16_rejects_invalid_colors() {
+        let text = BASE16.replace("\"0c0c0c\"", "\"0c0c0\"");
+        let err = load("yaml", &text, PaletteConfig::default()).unwrap_err();
+        assert!(
+            format!("{err:?}").contains("invalid color `0c0c0`"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn xresources_sets_the_colors_it_defines() {
+        let text = "\
+! comment
+#define unused #ffffff
+*.foreground: #aaaaaa
+*background:  #bbbbbb
+URxvt.cursorColor: #cccccc
+*.color0: #000000
+URxvt*color1: #010101
+XTerm.vt100.color15: #0f0f0f
+*.color16: #ffffff
+*.font: monospace
+";
+        let palette = load("Xresources", text, PaletteConfig::default()).unwrap();
+        let default = Palette::default();
+        assert_eq!(palette.named(NamedColor::Foreground), rgb("#aaaaaa"));
+        assert_eq!(palette.named(NamedColor::Background), rgb("#bbbbbb"));
+        assert_eq!(palette.cursor(), Some(rgb("#cccccc")));
+        assert_eq!(palette.rgb(Color::Indexed(0)), gray(0x0));
+        assert_eq!(palette.rgb(Color::Indexed(1)), gray(0x1));
+        assert_eq!(palette.rgb(Color::Indexed(15)), gray(0xf));
+        for i in 2..15 {
+            assert_eq!(
+                palette.rgb(Color::Indexed(i)),
+                default.rgb(Color::Indexed(i))
+            );
+        }
+        assert_eq!(palette.to_hex(Color::Indexed(16)), "#000000");
+    }
+
+    #[test]
+    fn xresources_rejects_invalid_colors() {
+        let err = load(
+            "Xresources",
+            "\n*.color3: yellow\n",
+            PaletteConfig::default(),
+        )
+        .unwrap_err();
+        assert!(
+            format!("{err:?}").contains("line 2: invalid color `yellow`"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn inline_colors_override_file() {
+        let config = PaletteConfig {
+            foreground: Some(rgb("#123456")),
+            ..PaletteConfig::default()
+        };
+        let palette = load("yaml", BASE16, config).unwrap();
+        assert_eq!(palette.named(NamedColor::Foreground), rgb("#123456"));
+        assert_eq!(palette.named(NamedColor::Background), gray(0x0));
+    }
+
+    #[test]
+    fn missing_file_fails() {
+        let config = PaletteConfig {
+            file: Some("/nonexistent/palette.yaml".into()),
+            ..PaletteConfig::default()
+        };
+        let err = Palette::load(&config).unwrap_err();
+        assert!(
+            format!("{err:?}").contains("failed to read palette /nonexistent/palette.yaml"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn terminal_colors_override_set_colors() {
+        let mut colors = vec![None; 16];
+        colors[2] = Some("#222222".to_string());
+        colors[9] = Some("Red".to_string());
+        let colors = TerminalColors {
+            colors,
+            normal: NormalColors {
+                fg: Some("#dddddd".to_string()),
+                bg: Some("#eeeeee".to_string()),
+                background: "dark".to_string(),
+            },
+        };
+        let palette = numbered().with_terminal_colors(&colors, TransparentBackground::Transparent);
+        for i in 0..16 {
+            let expected = if i == 2 { rgb("#222222") } else { gray(i) };
+            assert_eq!(palette.rgb(Color::Indexed(i)), expected, "index {i}");
+        }
+        assert_eq!(palette.named(NamedColor::Foreground), rgb("#dddddd"));
+        assert_eq!(palette.background(), Some(rgb("#eeeeee")));
+        assert_eq!(palette.cursor(), Some(rgb("#cccccc")));
+    }
+
+    /// Colors of a colorscheme that leaves Normal transparent.
+    fn transparent_colors(background: &str) -> TerminalColors {
+        TerminalColors {
+            colors: Vec::new(),
+            normal: NormalColors {
+                fg: None,
+                bg: None,
+                background: background.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn transparent_normal_keeps_palette_background() {
+        let palette = numbered()
+            .with_terminal_colors(&transparent_colors("light"), TransparentBackground::Palette);
+        assert_eq!(palette, numbered());
+    }
+
+    #[test]
+    fn transparent_normal_follows_background_option() {
+        let with_background = |background| {
+            numbered().with_terminal_colors(
+                &transparent_colors(background),
+                TransparentBackground::Background,
+            )
+        };
+        let light = with_background("light");
+        assert_eq!(light.named(NamedColor::Foreground), rgb("#4d4d4c"));
+        assert_eq!(light.background(), Some(rgb("#ffffff")));
+        let dark = with_background("dark");
+        assert_eq!(dark.named(NamedColor::Foreground), rgb("#c5c8c6"));
+        assert_eq!(dark.background(), Some(rgb("#1d1f21")));
+    }
+
+    #[test]
+    fn transparent_normal_keeps_its_foreground() {
+        let mut colors = transparent_colors("light");
+        colors.normal.fg = Some("#dddddd".to_string());
+        let palette = numbered().with_terminal_colors(&colors, TransparentBackground::Background);
+        assert_eq!(palette.named(NamedColor::Foreground), rgb("#dddddd"));
+        assert_eq!(palette.background(), Some(rgb("#ffffff")));
+    }
+
+    #[test]
+    fn transparent_normal_takes_configured_color() {
+        let palette = numbered().with_terminal_colors(
+            &transparent_colors("dark"),
+            TransparentBackground::Color(rgb("#123456")),
+        );
+        assert_eq!(palette.background(), Some(rgb("#123456")));
+        assert_eq!(palette.named(NamedColor::Foreground), rgb("#aaaaaa"));
+    }
+
+    #[test]
+    fn transparent_normal_leaves_default_background_transparent() {
+        let palette = numbered().with_terminal_colors(
+            &transparent_colors("dark"),
+            TransparentBackground::Transparent,
+        );
+        let background = Color::Named(NamedColor::Background);
+        assert_eq!(palette.background(), None);
+        assert_eq!(palette.to_bg_hex(background), "transparent");
+        assert_eq!(palette.to_hex(background), "#bbbbbb");
+        assert_eq!(palette.to_bg_hex(Color::Indexed(0)), "#000000");
+        assert_eq!(palette.to_bg_hex(Color::Spec(rgb("#bbbbbb"))), "#bbbbbb");
     }
 }
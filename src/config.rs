@@ -1,22 +1,302 @@
-use std::path::Path;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-use serde::Deserialize;
+use alacritty_terminal::term::color::Rgb;
+use serde::{Deserialize, Serialize};
+
+use crate::scenario::Action;
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub(crate) struct Config {
     pub(crate) plugins: Vec<Plugin>,
+    /// Scenario file to run instead of the bundled scenarios.
+    #[serde(default)]
+    pub(crate) scenarios: Option<PathBuf>,
+    /// Values of `background` every colorscheme is captured with, each one to its own directory.
+    /// Colorschemes are captured once with their default background if empty.
+    #[serde(default)]
+    pub(crate) backgrounds: Vec<Background>,
+    #[serde(default)]
+    pub(crate) png: PngOptions,
+    #[serde(default)]
+    pub(crate) html_styles: HtmlStyles,
+    /// Draws the cursor, with the shape and color nvim gives it, over snapshots and animations.
+    #[serde(default)]
+    pub(crate) cursor: bool,
+    /// Size every scenario starts at, scenarios can change it with `resize` steps.
+    #[serde(default)]
+    pub(crate) size: Size,
+    /// Colors of the terminal, for the cells nvim doesn't give an RGB color.
+    #[serde(default)]
+    pub(crate) palette: PaletteConfig,
+    #[serde(default)]
+    pub(crate) transparent_background: TransparentBackground,
+    #[serde(default)]
+    pub(crate) contrast: ContrastOptions,
+}
+
+/// What the cells of colorschemes whose Normal group has no background are drawn on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) enum TransparentBackground {
+    /// The background of the palette.
+    #[default]
+    Palette,
+    /// A light or dark default, depending on the value of `background` after loading the
+    /// colorscheme.
+    Background,
+    /// Nothing, leaving the background of HTML, SVG and PNG exports transparent.
+    Transparent,
+    Color(Rgb),
+}
+
+/// Parses `palette`, `background`, `transparent` or a `#rrggbb` color.
+impl TryFrom<String> for TransparentBackground {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "palette" => Ok(TransparentBackground::Palette),
+            "background" => Ok(TransparentBackground::Background),
+            "transparent" => Ok(TransparentBackground::Transparent),
+            color => color
+                .parse()
+                .map(TransparentBackground::Color)
+                .map_err(|_| {
+                    format!(
+                        "invalid transparent background `{color}`, expected palette, background, \
+                     transparent or a #rrggbb color"
+                    )
+                }),
+        }
+    }
+}
+
+/// Colors of the terminal, Tomorrow Night unless set here or in `file`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct PaletteConfig {
+    /// base16 scheme if its extension is `.yaml` or `.yml`, Xresources otherwise.
+    pub(crate) file: Option<PathBuf>,
+    /// The 16 base colors, overriding the ones of `file`.
+    pub(crate) colors: Option<[Rgb; 16]>,
+    pub(crate) foreground: Option<Rgb>,
+    pub(crate) background: Option<Rgb>,
+    /// Color of the cursor when nvim doesn't set one, the colors of the cell under it otherwise.
+    pub(crate) cursor: Option<Rgb>,
+}
+
+/// Size of the terminal nvim runs in, in cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "SizeFields")]
+pub(crate) struct Size {
+    pub(crate) columns: usize,
+    pub(crate) lines: usize,
+}
+
+impl Default for Size {
+    fn default() -> Self {
+        Size {
+            columns: 110,
+            lines: 40,
+        }
+    }
+}
+
+/// Parses sizes written as `{columns}x{lines}`, e.g. `110x40`.
+impl FromStr for Size {
+    type Err = String;
+
+    fn from_str(size: &str) -> Result<Self, Self::Err> {
+        let (columns, lines) = size
+            .split_once('x')
+            .and_then(|(columns, lines)| Some((columns.parse().ok()?, lines.parse().ok()?)))
+            .ok_or_else(|| format!("invalid size `{size}`, expected `{{columns}}x{{lines}}`"))?;
+        Size::try_from(SizeFields { columns, lines })
+    }
 }
 
 #[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SizeFields {
+    columns: usize,
+    lines: usize,
+}
+
+impl TryFrom<SizeFields> for Size {
+    type Error = String;
+
+    fn try_from(SizeFields { columns, lines }: SizeFields) -> Result<Self, Self::Error> {
+        // nvim refuses to go below 12 columns and 2 lines.
+        if columns < 12 || lines < 2 {
+            return Err(format!(
+                "size {columns}x{lines} is too small, nvim needs at least 12x2"
+            ));
+        }
+        Ok(Size { columns, lines })
+    }
+}
+
+/// How HTML snapshots are styled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum HtmlStyles {
+    /// Spans have their styles inline, so that every snapshot stands on its own.
+    #[default]
+    Inline,
+    /// Spans have classes defined in a `palette.css` shared by the snapshots of a colorscheme.
+    Classes,
+}
+
+/// Sizes, in pixels, used to rasterize snapshots to PNG and GIF.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct PngOptions {
+    pub(crate) cell_width: usize,
+    pub(crate) cell_height: usize,
+    pub(crate) font_size: f32,
+    /// Space around the grid, filled with the terminal's background color.
+    pub(crate) padding: usize,
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        PngOptions {
+            cell_width: 9,
+            cell_height: 18,
+            font_size: 15.0,
+            padding: 8,
+        }
+    }
+}
+
+/// Thresholds under which text is reported as hard to read in `contrast.json`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct ContrastOptions {
+    /// Minimum WCAG 2.x contrast ratio, 4.5 being the AA level for normal text.
+    pub(crate) min_wcag: f64,
+    /// Minimum APCA lightness contrast, in absolute Lc, 60 being the minimum for content text.
+    pub(crate) min_apca: f64,
+}
+
+impl Default for ContrastOptions {
+    fn default() -> Self {
+        ContrastOptions {
+            min_wcag: 4.5,
+            min_apca: 60.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Background {
+    Dark,
+    Light,
+}
+
+impl Background {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Background::Dark => "dark",
+            Background::Light => "light",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub(crate) struct Plugin {
     pub(crate) id: String,
     pub(crate) url: String,
     pub(crate) name: String,
     #[serde(default)]
     pub(crate) colorschemes: Vec<String>,
+    /// Steps run after the scenarios for every colorscheme of the plugin.
+    #[serde(default)]
+    pub(crate) extra_steps: Vec<Action>,
+    /// Steps run instead of the snapshot with the given title. The steps leading up to the snapshot
+    /// still run, so that they can't drop the setup of the scenario.
+    #[serde(default)]
+    pub(crate) replace_steps: HashMap<String, Vec<Action>>,
+    /// Overrides `transparentBackground` for the colorschemes of the plugin.
+    #[serde(default)]
+    pub(crate) transparent_background: Option<TransparentBackground>,
+}
+
+impl Plugin {
+    /// Name lazy.nvim gives the plugin, i.e. the name of its repository.
+    pub(crate) fn lazy_name(&self) -> &str {
+        let url = self.url.trim_end_matches('/').trim_end_matches(".git");
+        url.rsplit('/').next().unwrap_or(url)
+    }
 }
 
 pub(crate) fn load(path: &Path) -> Config {
     let file = std::fs::File::open(path).unwrap();
-    return serde_json::from_reader(file).unwrap();
+    serde_json::from_reader(file).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Size;
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(
+            "80x24".parse::<Size>(),
+            Ok(Size {
+                columns: 80,
+                lines: 24
+            })
+        );
+        assert_eq!(
+            "12x2".parse::<Size>(),
+            Ok(Size {
+                columns: 12,
+                lines: 2
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_sizes() {
+        for size in ["80", "80*24", "x24", "80x", "ax24", "-80x24"] {
+            assert_eq!(
+                size.parse::<Size>(),
+                Err(format!(
+                    "invalid size `{size}`, expected `{{columns}}x{{lines}}`"
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_sizes_nvim_cannot_run_in() {
+        assert_eq!(
+            "0x24".parse::<Size>(),
+            Err("size 0x24 is too small, nvim needs at least 12x2".to_string())
+        );
+        assert_eq!(
+            "11x24".parse::<Size>(),
+            Err("size 11x24 is too small, nvim needs at least 12x2".to_string())
+        );
+        assert_eq!(
+            "80x1".parse::<Size>(),
+            Err("size 80x1 is too small, nvim needs at least 12x2".to_string())
+        );
+    }
+
+    #[test]
+    fn deserialized_sizes_are_checked() {
+        let size: Size = serde_json::from_str(r#"{ "columns": 80, "lines": 24 }"#).unwrap();
+        assert_eq!(size, "80x24".parse().unwrap());
+        let err = serde_json::from_str::<Size>(r#"{ "columns": 0, "lines": 24 }"#).unwrap_err();
+        assert!(err.to_string().contains("size 0x24 is too small"), "{err}");
+    }
 }
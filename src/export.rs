@@ -0,0 +1,701 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use alacritty_terminal::{
+    ansi::{CursorShape, NamedColor},
+    event::VoidListener,
+    grid::Dimensions,
+    index::{Column, Line},
+    term::{
+        cell::{Cell, Flags},
+        color::Rgb,
+    },
+    Grid, Term,
+};
+use serde::Serialize;
+
+use crate::{
+    color::{self, Palette},
+    config::{Background, Config, Size},
+};
+
+mod ansi;
+mod contrast;
+mod gif;
+mod html;
+mod json;
+mod png;
+mod svg;
+
+/// Writes snapshots in a format.
+pub(crate) trait Exporter {
+    fn export(&mut self, snapshot: &Snapshot<'_>);
+
+    /// Whether snapshots passed to the exporter should come with the highlight groups of the
+    /// screen, which takes a while to query.
+    fn wants_highlights(&self) -> bool {
+        false
+    }
+
+    /// Formats that can't be animated ignore animations.
+    fn export_animation(&mut self, _animation: &Animation<'_>) {}
+
+    /// Called once every snapshot of a capture has been exported.
+    fn finish(&mut self, _capture: &Capture<'_>) {}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Format {
+    Html,
+    Svg,
+    Png,
+    Json,
+    Ansi,
+    Gif,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "html" => Ok(Format::Html),
+            "svg" => Ok(Format::Svg),
+            "png" => Ok(Format::Png),
+            "json" => Ok(Format::Json),
+            "ansi" => Ok(Format::Ansi),
+            "gif" => Ok(Format::Gif),
+            format => Err(format!(
+                "unknown format `{format}`, expected one of html, svg, png, json, ansi, gif"
+            )),
+        }
+    }
+}
+
+impl Format {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Format::Html => "html",
+            Format::Svg => "svg",
+            Format::Png => "png",
+            Format::Json => "json",
+            Format::Ansi => "ansi",
+            Format::Gif => "gif",
+        }
+    }
+
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            Format::Ansi => "ans",
+            format => format.as_str(),
+        }
+    }
+
+    /// Whether animations are exported to the format.
+    pub(crate) fn animates(self) -> bool {
+        matches!(self, Format::Svg | Format::Gif)
+    }
+}
+
+/// Run of a scenario for a colorscheme, whose snapshots are written to `out_dir`.
+pub(crate) struct Capture<'a> {
+    pub(crate) out_dir: PathBuf,
+    pub(crate) plugin_id: &'a str,
+    pub(crate) colorscheme: &'a str,
+    /// Value `background` was set to before loading the colorscheme, if any.
+    pub(crate) background: Option<Background>,
+    /// Whether snapshots and animations show the cursor.
+    pub(crate) cursor: bool,
+    pub(crate) palette: &'a Palette,
+}
+
+/// Screen captured during a capture, as passed to exporters.
+pub(crate) struct Snapshot<'a> {
+    pub(crate) capture: &'a Capture<'a>,
+    pub(crate) title: &'a str,
+    pub(crate) grid: &'a Grid<Cell>,
+    /// Lines of `grid` split into spans.
+    pub(crate) lines: &'a [Vec<Span>],
+    /// Highlight groups of the cells showing buffer text, if an exporter wants them.
+    pub(crate) highlights: Option<&'a Highlights>,
+    /// Cursor drawn over the snapshot, already accounted for in `lines`.
+    pub(crate) cursor: Option<&'a Cursor>,
+}
+
+/// Cursor as nvim last set it with DECSCUSR and OSC 12.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Cursor {
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) shape: Shape,
+    pub(crate) color: String,
+    /// Color of the text under a block cursor.
+    pub(crate) text_color: String,
+}
+
+impl Cursor {
+    /// Rectangle, as `[x, y, width, height]` in pixels from the top left corner of the cell,
+    /// drawn for a beam or underline cursor. Block cursors are drawn by swapping colors instead.
+    pub(crate) fn bar(&self, cell_width: usize, cell_height: usize) -> Option<[usize; 4]> {
+        match self.shape {
+            Shape::Block => None,
+            Shape::Beam => Some([0, 0, (cell_width / 4).max(1), cell_height]),
+            Shape::Underline => {
+                let thickness = (cell_height / 9).max(1);
+                Some([0, cell_height - thickness, cell_width, thickness])
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Shape {
+    Block,
+    Beam,
+    Underline,
+}
+
+/// Screens taken between a `start_animation` and a `stop_animation` step, as passed to exporters.
+pub(crate) struct Animation<'a> {
+    pub(crate) capture: &'a Capture<'a>,
+    pub(crate) title: &'a str,
+    pub(crate) frames: &'a [Frame],
+}
+
+pub(crate) struct Frame {
+    /// Lines of the screen split into spans.
+    pub(crate) lines: Vec<Vec<Span>>,
+    /// Time the frame is shown before the next one.
+    pub(crate) duration: Duration,
+}
+
+/// Highlight group of the cells at `(line, column)`.
+pub(crate) type Highlights = HashMap<(usize, usize), String>;
+
+/// Returns an exporter for every format in `formats`, along with the one writing the contrast
+/// report every capture gets, meant to be used for a single capture.
+pub(crate) fn exporters(formats: &[Format], config: &Config) -> Vec<Box<dyn Exporter>> {
+    let mut exporters = formats
+        .iter()
+        .map(|format| -> Box<dyn Exporter> {
+            match format {
+                Format::Html => Box::new(html::Html::new(config.html_styles)),
+                Format::Svg => Box::new(svg::Svg),
+                Format::Png => Box::new(png::Png::new(config.png)),
+                Format::Json => Box::new(json::Json),
+                Format::Ansi => Box::new(ansi::Ansi),
+                Format::Gif => Box::new(gif::Gif::new(config.png)),
+            }
+        })
+        .collect::<Vec<_>>();
+    exporters.push(Box::new(contrast::Contrast::new(config.contrast)));
+    exporters
+}
+
+/// Flags that change how text looks once its colors are resolved.
+const STYLE_FLAGS: Flags = Flags::BOLD
+    .union(Flags::ITALIC)
+    .union(Flags::STRIKEOUT)
+    .union(Flags::ALL_UNDERLINES);
+
+/// Run of cells of a line that share the same colors and style.
+#[derive(Debug)]
+pub(crate) struct Span {
+    pub(crate) bg: String,
+    pub(crate) fg: String,
+    /// Only the flags in `STYLE_FLAGS`, the colors already account for inverse, dim and hidden
+    /// cells.
+    pub(crate) flags: Flags,
+    /// Color of underlines, the foreground color is used when unset.
+    pub(crate) underline_color: Option<String>,
+    pub(crate) cells: Vec<CellText>,
+    /// Column of the first cell of the span.
+    pub(crate) column: usize,
+    /// Number of cells the span covers.
+    pub(crate) width: usize,
+    /// Cursor drawn over the span, which then only covers the cell under the cursor. The colors
+    /// of the span already account for a block cursor.
+    pub(crate) cursor: Option<Cursor>,
+}
+
+/// Text of a cell of a span.
+#[derive(Debug)]
+pub(crate) struct CellText {
+    /// The char of the cell followed by its zero-width chars.
+    pub(crate) text: String,
+    /// Whether the cell, and the spacer that follows it, make a single wide char.
+    pub(crate) wide: bool,
+}
+
+/// Passes the snapshot `title` of `grid` to every exporter.
+pub(crate) fn snapshot(
+    exporters: &mut [Box<dyn Exporter>],
+    capture: &Capture<'_>,
+    grid: &Grid<Cell>,
+    cursor: Option<&Cursor>,
+    title: &str,
+    highlights: Option<&Highlights>,
+) {
+    let lines = spans(grid, cursor, capture.palette);
+    let snapshot = Snapshot {
+        capture,
+        title,
+        grid,
+        lines: &lines,
+        highlights,
+        cursor,
+    };
+    for exporter in exporters {
+        exporter.export(&snapshot);
+    }
+}
+
+/// Passes the animation `title` made of `frames` to every exporter.
+pub(crate) fn animation(
+    exporters: &mut [Box<dyn Exporter>],
+    capture: &Capture<'_>,
+    title: &str,
+    frames: &[Frame],
+) {
+    let animation = Animation {
+        capture,
+        title,
+        frames,
+    };
+    for exporter in exporters {
+        exporter.export_animation(&animation);
+    }
+}
+
+/// Renders `grid` as the sequences that redraw it, and move the cursor back, in a terminal.
+pub(crate) fn ansi_screen(grid: &Grid<Cell>, palette: &Palette) -> String {
+    let point = grid.cursor.point;
+    format!(
+        "\x1b[H{}\x1b[{};{}H",
+        ansi::render(&spans(grid, None, palette)).join("\r\n"),
+        point.line.0 + 1,
+        point.column.0 + 1
+    )
+}
+
+/// Returns the cursor of `term`, or `None` if nvim hid it.
+pub(crate) fn cursor(term: &Term<VoidListener>, palette: &Palette) -> Option<Cursor> {
+    let renderable = term.renderable_content().cursor;
+    let shape = match renderable.shape {
+        CursorShape::Block | CursorShape::HollowBlock => Shape::Block,
+        CursorShape::Beam => Shape::Beam,
+        CursorShape::Underline => Shape::Underline,
+        CursorShape::Hidden => return None,
+    };
+    let point = renderable.point;
+    let cell = &term.grid()[point];
+    // Like most terminals, the cursor takes the colors of the cell under it unless nvim or the
+    // palette sets its color.
+    let (fg, mut bg) = colors(cell, palette);
+    // Text under a block cursor would disappear with a transparent color.
+    if bg == color::TRANSPARENT {
+        bg = palette.named(NamedColor::Background).to_string();
+    }
+    let color = term.colors()[NamedColor::Cursor]
+        .or(palette.cursor())
+        .map_or(fg, |rgb| rgb.to_string());
+    Some(Cursor {
+        line: point.line.0.max(0) as usize,
+        column: point.column.0,
+        shape,
+        color,
+        text_color: bg,
+    })
+}
+
+/// Splits every line of `grid` into spans, with the cell under `cursor` in a span of its own.
+pub(crate) fn spans(
+    grid: &Grid<Cell>,
+    cursor: Option<&Cursor>,
+    palette: &Palette,
+) -> Vec<Vec<Span>> {
+    let mut lines: Vec<Vec<Span>> = Vec::new();
+    for line in 0..grid.screen_lines() {
+        let mut line_spans: Vec<Span> = Vec::new();
+        let row = &grid[Line(line as i32)];
+        for col in 0..grid.columns() {
+            let cell = &row[Column(col)];
+            // Spacers are covered by the wide char before them.
+            let Some(text) = cell_text(cell) else {
+                continue;
+            };
+            let wide = cell.flags.contains(Flags::WIDE_CHAR);
+            let cell_width = if wide { 2 } else { 1 };
+            let (mut fg, mut bg) = colors(cell, palette);
+            let flags = cell.flags & STYLE_FLAGS;
+            let underline_color = cell.underline_color().map(|color| palette.to_hex(color));
+            let cursor = cursor
+                .filter(|cursor| cursor.line == line && cursor.column == col)
+                .cloned();
+            if let Some(Cursor {
+                shape: Shape::Block,
+                color,
+                text_color,
+                ..
+            }) = &cursor
+            {
+                (fg, bg) = (text_color.clone(), color.clone());
+            }
+            let merge_with_last_span = cursor.is_none()
+                && line_spans.last_mut().is_some_and(|span| {
+                    span.cursor.is_none()
+                        && span.bg == bg
+                        && span.fg == fg
+                        && span.flags == flags
+                        && span.underline_color == underline_color
+                });
+            if merge_with_last_span {
+                let span = line_spans.last_mut().unwrap();
+                span.cells.push(CellText { text, wide });
+                span.width += cell_width;
+            } else {
+                line_spans.push(Span {
+                    bg,
+                    fg,
+                    flags,
+                    underline_color,
+                    cells: vec![CellText { text, wide }],
+                    column: col,
+                    width: cell_width,
+                    cursor,
+                });
+            }
+        }
+        lines.push(line_spans);
+    }
+    lines
+}
+
+/// Size of the screen `lines` were split from.
+pub(crate) fn size(lines: &[Vec<Span>]) -> Size {
+    Size {
+        columns: lines
+            .first()
+            .map_or(0, |line| line.iter().map(|span| span.width).sum()),
+        lines: lines.len(),
+    }
+}
+
+/// Returns the char of `cell` followed by its zero-width chars, or `None` if the cell is the
+/// spacer of a wide char.
+pub(crate) fn cell_text(cell: &Cell) -> Option<String> {
+    if cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
+        return None;
+    }
+    let mut text = cell.c.to_string();
+    text.extend(cell.zerowidth().into_iter().flatten());
+    Some(text)
+}
+
+/// Foreground and background colors `cell` is drawn with.
+pub(crate) fn colors(cell: &Cell, palette: &Palette) -> (String, String) {
+    let (fg, bg) = if cell.flags.contains(Flags::INVERSE) {
+        (cell.bg, cell.fg)
+    } else {
+        (cell.fg, cell.bg)
+    };
+    let bg = palette.to_bg_hex(bg);
+    let fg = if cell.flags.contains(Flags::HIDDEN) {
+        bg.clone()
+    } else if cell.flags.contains(Flags::DIM) {
+        palette.dim(fg)
+    } else {
+        palette.to_hex(fg)
+    };
+    (fg, bg)
+}
+
+impl Span {
+    pub(crate) fn text(&self) -> String {
+        self.cells.iter().map(|cell| cell.text.as_str()).collect()
+    }
+
+    /// CSS declarations for the flags of the span, shared by the HTML and SVG exporters.
+    pub(crate) fn flags_css(&self) -> String {
+        let mut css = String::new();
+        if self.flags.contains(Flags::BOLD) {
+            css.push_str("font-weight:bold;");
+        }
+        if self.flags.contains(Flags::ITALIC) {
+            css.push_str("font-style:italic;");
+        }
+        let mut decoration_lines = Vec::new();
+        if self.flags.intersects(Flags::ALL_UNDERLINES) {
+            decoration_lines.push("underline");
+        }
+        if self.flags.contains(Flags::STRIKEOUT) {
+            decoration_lines.push("line-through");
+        }
+        if decoration_lines.is_empty() {
+            return css;
+        }
+        css.push_str(&format!(
+            "text-decoration-line:{};",
+            decoration_lines.join(" ")
+        ));
+        let decoration_style = if self.flags.contains(Flags::UNDERCURL) {
+            Some("wavy")
+        } else if self.flags.contains(Flags::DOUBLE_UNDERLINE) {
+            Some("double")
+        } else if self.flags.contains(Flags::DOTTED_UNDERLINE) {
+            Some("dotted")
+        } else if self.flags.contains(Flags::DASHED_UNDERLINE) {
+            Some("dashed")
+        } else {
+            None
+        };
+        if let Some(decoration_style) = decoration_style {
+            css.push_str(&format!("text-decoration-style:{decoration_style};"));
+        }
+        if let Some(underline_color) = &self.underline_color {
+            css.push_str(&format!("text-decoration-color:{underline_color};"));
+        }
+        css
+    }
+}
+
+/// Parses a color of a span.
+pub(crate) fn rgb(color: &str) -> Rgb {
+    color.parse().unwrap_or_default()
+}
+
+/// Escapes `text` so that it can be used as the content of an HTML or SVG element.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Creates, or truncates, `out_dir/{title}.{extension}`.
+fn create(out_dir: &Path, title: &str, extension: &str) -> File {
+    std::fs::create_dir_all(out_dir).unwrap();
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(out_dir.join(format!("{title}.{extension}")))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use alacritty_terminal::{
+        ansi::Color,
+        index::{Column, Line},
+        term::{
+            cell::{Cell, Flags},
+            color::Rgb,
+        },
+        Grid,
+    };
+
+    use super::{colors, spans};
+    use crate::color::Palette;
+
+    const COLUMNS: usize = 110;
+    const LINES: usize = 40;
+
+    fn grid(cells: &[Cell]) -> Grid<Cell> {
+        let mut grid = Grid::new(LINES, COLUMNS, 0);
+        for (col, cell) in cells.iter().enumerate() {
+            grid[Line(0)][Column(col)] = cell.clone();
+        }
+        grid
+    }
+
+    fn cell(c: char) -> Cell {
+        Cell {
+            c,
+            ..Cell::default()
+        }
+    }
+
+    fn wide(c: char) -> [Cell; 2] {
+        let mut cell = cell(c);
+        cell.flags.insert(Flags::WIDE_CHAR);
+        let mut spacer = Cell::default();
+        spacer.flags.insert(Flags::WIDE_CHAR_SPACER);
+        [cell, spacer]
+    }
+
+    #[test]
+    fn wide_chars_skip_their_spacer() {
+        let mut cells = vec![cell('a')];
+        cells.extend(wide('漢'));
+        cells.extend(wide('字'));
+        cells.push(cell('b'));
+        let lines = spans(&grid(&cells), None, &Palette::default());
+
+        let text = lines[0][0].text();
+        assert!(text.starts_with("a漢字b "), "{text:?}");
+        assert_eq!(text.chars().count(), COLUMNS - 2);
+        assert_eq!(lines[0][0].width, COLUMNS);
+        assert!(lines[0][0].cells[1].wide);
+        assert!(!lines[0][0].cells[3].wide);
+    }
+
+    #[test]
+    fn wide_chars_keep_following_spans_aligned() {
+        let mut cells = wide('󰈙').to_vec();
+        let mut red = cell('r');
+        red.fg = Color::Spec(Rgb {
+            r: 0xff,
+            g: 0,
+            b: 0,
+        });
+        cells.push(red);
+        let lines = spans(&grid(&cells), None, &Palette::default());
+
+        assert_eq!(lines[0][0].text(), "󰈙");
+        assert_eq!(lines[0][0].width, 2);
+        assert_eq!(lines[0][1].column, 2);
+        assert_eq!(lines[0][1].text(), "r");
+        assert_eq!(lines[0][2].column, 3);
+    }
+
+    #[test]
+    fn zero_width_chars_are_appended_to_their_cell() {
+        let mut accented = cell('e');
+        accented.push_zerowidth('\u{301}');
+        let lines = spans(&grid(&[accented, cell('x')]), None, &Palette::default());
+
+        assert_eq!(lines[0][0].cells[0].text, "e\u{301}");
+        assert!(lines[0][0].text().starts_with("e\u{301}x"));
+        assert_eq!(lines[0][0].width, COLUMNS);
+    }
+
+    #[test]
+    fn every_line_covers_every_column() {
+        let mut cells = Vec::new();
+        for _ in 0..COLUMNS / 2 {
+            cells.extend(wide('字'));
+        }
+        let lines = spans(&grid(&cells), None, &Palette::default());
+
+        for line in &lines {
+            assert_eq!(line.iter().map(|span| span.width).sum::<usize>(), COLUMNS);
+        }
+    }
+
+    fn spec(hex: &str) -> Color {
+        Color::Spec(hex.parse().unwrap())
+    }
+
+    fn styled(flags: Flags) -> Cell {
+        let mut cell = cell('x');
+        cell.fg = spec("#ffffff");
+        cell.bg = spec("#102030");
+        cell.flags = flags;
+        cell
+    }
+
+    fn cell_colors(flags: Flags) -> (String, String) {
+        colors(&styled(flags), &Palette::default())
+    }
+
+    #[test]
+    fn plain_cells_keep_their_colors() {
+        assert_eq!(
+            cell_colors(Flags::empty()),
+            ("#ffffff".to_string(), "#102030".to_string())
+        );
+    }
+
+    #[test]
+    fn inverse_cells_swap_their_colors() {
+        assert_eq!(
+            cell_colors(Flags::INVERSE),
+            ("#102030".to_string(), "#ffffff".to_string())
+        );
+    }
+
+    #[test]
+    fn dim_cells_darken_their_foreground() {
+        assert_eq!(
+            cell_colors(Flags::DIM),
+            ("#a8a8a8".to_string(), "#102030".to_string())
+        );
+    }
+
+    #[test]
+    fn hidden_cells_draw_their_text_with_the_background() {
+        assert_eq!(
+            cell_colors(Flags::HIDDEN),
+            ("#102030".to_string(), "#102030".to_string())
+        );
+        assert_eq!(
+            cell_colors(Flags::HIDDEN | Flags::INVERSE),
+            ("#ffffff".to_string(), "#ffffff".to_string())
+        );
+    }
+
+    fn flags_css(cell: Cell) -> String {
+        let lines = spans(&grid(&[cell]), None, &Palette::default());
+        lines[0][0].flags_css()
+    }
+
+    #[test]
+    fn spans_keep_only_style_flags() {
+        let lines = spans(
+            &grid(&[styled(Flags::BOLD | Flags::INVERSE | Flags::DIM)]),
+            None,
+            &Palette::default(),
+        );
+        assert_eq!(lines[0][0].flags, Flags::BOLD);
+    }
+
+    #[test]
+    fn flags_css_for_bold_italic_and_strikeout() {
+        assert_eq!(flags_css(styled(Flags::empty())), "");
+        assert_eq!(
+            flags_css(styled(Flags::BOLD | Flags::ITALIC)),
+            "font-weight:bold;font-style:italic;"
+        );
+        assert_eq!(
+            flags_css(styled(Flags::STRIKEOUT)),
+            "text-decoration-line:line-through;"
+        );
+    }
+
+    #[test]
+    fn flags_css_for_underlines() {
+        assert_eq!(
+            flags_css(styled(Flags::UNDERLINE)),
+            "text-decoration-line:underline;"
+        );
+        assert_eq!(
+            flags_css(styled(Flags::UNDERCURL | Flags::STRIKEOUT)),
+            "text-decoration-line:underline line-through;text-decoration-style:wavy;"
+        );
+        assert_eq!(
+            flags_css(styled(Flags::DOUBLE_UNDERLINE)),
+            "text-decoration-line:underline;text-decoration-style:double;"
+        );
+        let mut colored = styled(Flags::UNDERCURL);
+        colored.set_underline_color(Some(spec("#ff0000")));
+        assert_eq!(
+            flags_css(colored),
+            "text-decoration-line:underline;text-decoration-style:wavy;\
+             text-decoration-color:#ff0000;"
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use std::{
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use alacritty_terminal::term::{cell::Flags, color::Rgb};
+
+use super::{Exporter, Shape, Snapshot, Span};
+use crate::color;
+
+/// Writes snapshots as text with truecolor SGR sequences, to be printed in a terminal.
+pub(crate) struct Ansi;
+
+impl Exporter for Ansi {
+    fn export(&mut self, snapshot: &Snapshot<'_>) {
+        export(&snapshot.capture.out_dir, snapshot.lines, snapshot.title);
+    }
+}
+
+/// Attributes set by the SGR sequences written so far.
+struct Sgr<'a> {
+    fg: Option<&'a str>,
+    bg: Option<&'a str>,
+    flags: Flags,
+    underline_color: Option<&'a str>,
+}
+
+impl<'a> Sgr<'a> {
+    /// Attributes right after a reset.
+    fn reset() -> Sgr<'a> {
+        Sgr {
+            fg: None,
+            bg: None,
+            flags: Flags::empty(),
+            underline_color: None,
+        }
+    }
+
+    /// Attributes of `span`. Text can't show a beam, so a beam cursor is drawn as a block.
+    fn new(span: &'a Span) -> Sgr<'a> {
+        let mut sgr = Sgr {
+            fg: Some(&span.fg),
+            bg: Some(&span.bg),
+            flags: span.flags,
+            underline_color: span.underline_color.as_deref(),
+        };
+        if let Some(cursor) = &span.cursor {
+            match cursor.shape {
+                Shape::Block => {}
+                Shape::Beam => {
+                    sgr.fg = Some(&cursor.text_color);
+                    sgr.bg = Some(&cursor.color);
+                }
+                Shape::Underline => {
+                    sgr.flags = (sgr.flags - Flags::ALL_UNDERLINES) | Flags::UNDERLINE;
+                    sgr.underline_color = Some(&cursor.color);
+                }
+            }
+        }
+        sgr
+    }
+
+    /// Parameters of the SGR sequence that changes the attributes from `self` to `next`.
+    fn diff(&self, next: &Sgr<'_>) -> Vec<String> {
+        let mut params = Vec::new();
+        if self.fg != next.fg {
+            params.push(color_param("38", "39", next.fg));
+        }
+        if self.bg != next.bg {
+            params.push(color_param("48", "49", next.bg));
+        }
+        let flag_params = [
+            (Flags::BOLD, "1", "22"),
+            (Flags::ITALIC, "3", "23"),
+            (Flags::STRIKEOUT, "9", "29"),
+        ];
+        for (flag, on, off) in flag_params {
+            if self.flags.contains(flag) != next.flags.contains(flag) {
+                params.push(if next.flags.contains(flag) { on } else { off }.to_string());
+            }
+        }
+        let underline = |flags: Flags| flags & Flags::ALL_UNDERLINES;
+        if underline(self.flags) != underline(next.flags) {
+            params.push(underline_param(next.flags).to_string());
+        }
+        if self.underline_color != next.underline_color {
+            params.push(match next.underline_color {
+                Some(color) => format!("58;2;{}", rgb_params(Some(color))),
+                None => "59".to_string(),
+            });
+        }
+        params
+    }
+}
+
+/// Parameter setting a truecolor `color`, or the terminal's default one if `color` is
+/// transparent.
+fn color_param(set: &str, default: &str, color: Option<&str>) -> String {
+    if color == Some(color::TRANSPARENT) {
+        default.to_string()
+    } else {
+        format!("{set};2;{}", rgb_params(color))
+    }
+}
+
+fn rgb_params(color: Option<&str>) -> String {
+    let Rgb { r, g, b } = color.map(super::rgb).unwrap_or_default();
+    format!("{r};{g};{b}")
+}
+
+fn underline_param(flags: Flags) -> &'static str {
+    if flags.contains(Flags::UNDERCURL) {
+        "4:3"
+    } else if flags.contains(Flags::DOUBLE_UNDERLINE) {
+        "4:2"
+    } else if flags.contains(Flags::DOTTED_UNDERLINE) {
+        "4:4"
+    } else if flags.contains(Flags::DASHED_UNDERLINE) {
+        "4:5"
+    } else if flags.contains(Flags::UNDERLINE) {
+        "4"
+    } else {
+        "24"
+    }
+}
+
+/// Renders every line with the SGR sequences that set its attributes, ending in a reset so
+/// that the background doesn't leak past the screen.
+pub(crate) fn render(lines: &[Vec<Span>]) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| {
+            let mut text = String::new();
+            let mut sgr = Sgr::reset();
+            for span in line {
+                let next = Sgr::new(span);
+                let params = sgr.diff(&next);
+                if !params.is_empty() {
+                    text.push_str(&format!("\x1b[{}m", params.join(";")));
+                }
+                text.push_str(&span.text());
+                sgr = next;
+            }
+            text.push_str("\x1b[0m");
+            text
+        })
+        .collect()
+}
+
+fn export(out_dir: &Path, lines: &[Vec<Span>], title: &str) {
+    let mut file = BufWriter::new(super::create(out_dir, title, "ans"));
+    for line in render(lines) {
+        writeln!(file, "{line}").unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alacritty_terminal::term::cell::Flags;
+
+    use super::render;
+    use crate::export::{CellText, Span};
+
+    fn span(fg: &str, bg: &str, flags: Flags, text: &str) -> Span {
+        let cells = text
+            .chars()
+            .map(|c| CellText {
+                text: c.to_string(),
+                wide: false,
+            })
+            .collect::<Vec<_>>();
+        Span {
+            bg: bg.to_string(),
+            fg: fg.to_string(),
+            flags,
+            underline_color: None,
+            width: cells.len(),
+            cells,
+            column: 0,
+            cursor: None,
+        }
+    }
+
+    #[test]
+    fn first_span_sets_every_attribute() {
+        let lines = render(&[vec![span("#ff0000", "#000000", Flags::BOLD, "a")]]);
+        assert_eq!(lines, ["\x1b[38;2;255;0;0;48;2;0;0;0;1ma\x1b[0m"]);
+    }
+
+    #[test]
+    fn unchanged_attributes_write_no_sequence() {
+        let lines = render(&[vec![
+            span("#ff0000", "#000000", Flags::empty(), "ab"),
+            span("#ff0000", "#000000", Flags::empty(), "cd"),
+        ]]);
+        assert_eq!(lines, ["\x1b[38;2;255;0;0;48;2;0;0;0mabcd\x1b[0m"]);
+    }
+
+    #[test]
+    fn only_changed_attributes_are_written() {
+        let lines = render(&[vec![
+            span("#ff0000", "#000000", Flags::BOLD | Flags::ITALIC, "a"),
+            span("#00ff00", "#000000", Flags::ITALIC, "b"),
+            span("#00ff00", "#0000ff", Flags::ITALIC, "c"),
+        ]]);
+        assert_eq!(
+            lines,
+            ["\x1b[38;2;255;0;0;48;2;0;0;0;1;3ma\x1b[38;2;0;255;0;22mb\x1b[48;2;0;0;255mc\x1b[0m"]
+        );
+    }
+
+    #[test]
+    fn attributes_are_reset_to_their_default() {
+        let mut underlined = span(
+            "#ffffff",
+            "#000000",
+            Flags::UNDERCURL | Flags::STRIKEOUT | Flags::ITALIC,
+            "a",
+        );
+        underlined.underline_color = Some("#ff0000".to_string());
+        let lines = render(&[vec![
+            underlined,
+            span("#ffffff", "#000000", Flags::empty(), "b"),
+        ]]);
+        assert_eq!(
+            lines,
+            ["\x1b[38;2;255;255;255;48;2;0;0;0;3;9;4:3;58;2;255;0;0ma\x1b[23;29;24;59mb\x1b[0m"]
+        );
+    }
+
+    #[test]
+    fn transparent_colors_use_the_terminal_defaults() {
+        let lines = render(&[vec![
+            span("#ffffff", "transparent", Flags::empty(), "a"),
+            span("transparent", "#000000", Flags::empty(), "b"),
+            span("transparent", "transparent", Flags::empty(), "c"),
+        ]]);
+        assert_eq!(
+            lines,
+            ["\x1b[38;2;255;255;255;49ma\x1b[39;48;2;0;0;0mb\x1b[49mc\x1b[0m"]
+        );
+    }
+
+    #[test]
+    fn wide_chars_are_written_once() {
+        let mut wide = span("#ffffff", "#000000", Flags::empty(), "a漢b");
+        wide.cells[1].wide = true;
+        wide.width = 4;
+        let lines = render(&[vec![wide]]);
+        assert_eq!(lines, ["\x1b[38;2;255;255;255;48;2;0;0;0ma漢b\x1b[0m"]);
+    }
+
+    #[test]
+    fn every_line_starts_from_a_reset() {
+        let line = || vec![span("#ff0000", "#000000", Flags::empty(), "a")];
+        let lines = render(&[line(), line()]);
+        assert_eq!(lines[0], lines[1]);
+    }
+}
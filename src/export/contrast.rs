@@ -0,0 +1,353 @@
+//! Readability of the text of a capture's snapshots, written to `contrast.json`.
+//!
+//! Schema, version 1:
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "plugin": "io.github.folke.tokyonight-nvim",
+//!   "colorscheme": "tokyonight",
+//!   "background": "dark" | "light" | null,
+//!   "thresholds": { "wcag": 4.5, "apca": 60 },
+//!   "cells": 5120,                // cells showing text in every snapshot
+//!   "failingWcag": 0.12,          // share of those cells under the WCAG threshold
+//!   "failingApca": 0.31,          // share of those cells under the APCA threshold
+//!   "screens": [                  // in the order they were taken
+//!     { "title": "Rust", "cells": 1024, "failingWcag": 0.1, "failingApca": 0.25 }
+//!   ],
+//!   "pairs": [                    // every text color over every background, worst WCAG first
+//!     {
+//!       "fg": "#565f89",
+//!       "bg": "#1a1b26",
+//!       "wcag": 2.97,             // contrast ratio, from 1 to 21
+//!       "apca": -22.6,            // Lc, negative for light text over a dark background
+//!       "failsWcag": true,
+//!       "failsApca": true,
+//!       "cells": 311,
+//!       "screens": ["Rust"]
+//!     }
+//!   ]
+//! }
+//! ```
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::BufWriter,
+};
+
+use alacritty_terminal::{ansi::NamedColor, term::color::Rgb};
+use serde::Serialize;
+
+use super::{Capture, Exporter, Snapshot};
+use crate::{
+    color,
+    config::{Background, ContrastOptions},
+};
+
+const VERSION: u32 = 1;
+
+/// Collects the colors of the text of every snapshot of a capture.
+pub(crate) struct Contrast {
+    options: ContrastOptions,
+    /// Cells by colors, then by screen.
+    pairs: BTreeMap<(String, String), BTreeMap<String, usize>>,
+    /// Titles of the snapshots, in the order they were taken.
+    titles: Vec<String>,
+}
+
+impl Contrast {
+    pub(crate) fn new(options: ContrastOptions) -> Contrast {
+        Contrast {
+            options,
+            pairs: BTreeMap::new(),
+            titles: Vec::new(),
+        }
+    }
+}
+
+impl Exporter for Contrast {
+    fn export(&mut self, snapshot: &Snapshot<'_>) {
+        let background = snapshot
+            .capture
+            .palette
+            .named(NamedColor::Background)
+            .to_string();
+        for span in snapshot.lines.iter().flatten() {
+            // The cursor isn't part of the colorscheme's text.
+            if span.cursor.is_some() {
+                continue;
+            }
+            let cells = span
+                .cells
+                .iter()
+                .filter(|cell| !cell.text.trim().is_empty())
+                .count();
+            if cells == 0 {
+                continue;
+            }
+            // Transparent backgrounds are judged against the one they stand for.
+            let bg = if span.bg == color::TRANSPARENT {
+                &background
+            } else {
+                &span.bg
+            };
+            *self
+                .pairs
+                .entry((span.fg.clone(), bg.clone()))
+                .or_default()
+                .entry(snapshot.title.to_string())
+                .or_default() += cells;
+        }
+        self.titles.push(snapshot.title.to_string());
+    }
+
+    fn finish(&mut self, capture: &Capture<'_>) {
+        export(capture, &self.report(capture));
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report<'a> {
+    version: u32,
+    plugin: &'a str,
+    colorscheme: &'a str,
+    background: Option<Background>,
+    thresholds: Thresholds,
+    #[serde(flatten)]
+    summary: Summary,
+    screens: Vec<ScreenSummary<'a>>,
+    pairs: Vec<Pair<'a>>,
+}
+
+#[derive(Serialize)]
+struct Thresholds {
+    wcag: f64,
+    apca: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Summary {
+    cells: usize,
+    failing_wcag: f64,
+    failing_apca: f64,
+}
+
+#[derive(Serialize)]
+struct ScreenSummary<'a> {
+    title: &'a str,
+    #[serde(flatten)]
+    summary: Summary,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Pair<'a> {
+    fg: &'a str,
+    bg: &'a str,
+    #[serde(serialize_with = "serialize_rounded")]
+    wcag: f64,
+    #[serde(serialize_with = "serialize_rounded")]
+    apca: f64,
+    fails_wcag: bool,
+    fails_apca: bool,
+    cells: usize,
+    screens: BTreeSet<&'a str>,
+}
+
+/// Cells showing text, and how many of them fail each threshold.
+#[derive(Default)]
+struct Counts {
+    cells: usize,
+    failing_wcag: usize,
+    failing_apca: usize,
+}
+
+impl Counts {
+    fn add(&mut self, pair: &Pair<'_>, cells: usize) {
+        self.cells += cells;
+        if pair.fails_wcag {
+            self.failing_wcag += cells;
+        }
+        if pair.fails_apca {
+            self.failing_apca += cells;
+        }
+    }
+
+    fn summary(&self) -> Summary {
+        let share = |failing: usize| {
+            let share = failing as f64 / self.cells.max(1) as f64;
+            (share * 10000.0).round() / 10000.0
+        };
+        Summary {
+            cells: self.cells,
+            failing_wcag: share(self.failing_wcag),
+            failing_apca: share(self.failing_apca),
+        }
+    }
+}
+
+impl Contrast {
+    fn report<'a>(&'a self, capture: &'a Capture<'_>) -> Report<'a> {
+        let ContrastOptions { min_wcag, min_apca } = self.options;
+        let mut counts = Counts::default();
+        let mut screen_counts = self
+            .titles
+            .iter()
+            .map(|title| (title, Counts::default()))
+            .collect::<Vec<_>>();
+        let mut pairs = Vec::new();
+        for ((fg, bg), cells_by_screen) in &self.pairs {
+            let (text, background) = (super::rgb(fg), super::rgb(bg));
+            let wcag = wcag_ratio(text, background);
+            let apca = apca_contrast(text, background);
+            let pair = Pair {
+                fg,
+                bg,
+                wcag,
+                apca,
+                fails_wcag: wcag < min_wcag,
+                fails_apca: apca.abs() < min_apca,
+                cells: cells_by_screen.values().sum(),
+                screens: cells_by_screen.keys().map(String::as_str).collect(),
+            };
+            counts.add(&pair, pair.cells);
+            for (title, counts) in &mut screen_counts {
+                if let Some(&cells) = cells_by_screen.get(*title) {
+                    counts.add(&pair, cells);
+                }
+            }
+            pairs.push(pair);
+        }
+        pairs.sort_by(|a, b| {
+            a.wcag
+                .total_cmp(&b.wcag)
+                .then(a.apca.abs().total_cmp(&b.apca.abs()))
+        });
+        Report {
+            version: VERSION,
+            plugin: capture.plugin_id,
+            colorscheme: capture.colorscheme,
+            background: capture.background,
+            thresholds: Thresholds {
+                wcag: min_wcag,
+                apca: min_apca,
+            },
+            summary: counts.summary(),
+            screens: screen_counts
+                .iter()
+                .map(|(title, counts)| ScreenSummary {
+                    title,
+                    summary: counts.summary(),
+                })
+                .collect(),
+            pairs,
+        }
+    }
+}
+
+fn export(capture: &Capture<'_>, report: &Report<'_>) {
+    let file = super::create(&capture.out_dir, "contrast", "json");
+    serde_json::to_writer_pretty(BufWriter::new(file), report).unwrap();
+}
+
+/// Serializes `value` with two decimals, which is all a contrast needs.
+fn serialize_rounded<S: serde::Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64((value * 100.0).round() / 100.0)
+}
+
+/// Contrast ratio of WCAG 2.x, from 1 for identical luminances to 21 for black and white.
+pub(crate) fn wcag_ratio(fg: Rgb, bg: Rgb) -> f64 {
+    let luminance = |rgb: Rgb| {
+        let linear = |channel: u8| {
+            let channel = channel as f64 / 255.0;
+            if channel <= 0.04045 {
+                channel / 12.92
+            } else {
+                ((channel + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(rgb.r) + 0.7152 * linear(rgb.g) + 0.0722 * linear(rgb.b)
+    };
+    let (fg, bg) = (luminance(fg), luminance(bg));
+    (fg.max(bg) + 0.05) / (fg.min(bg) + 0.05)
+}
+
+/// Lightness contrast of APCA 0.0.98G-4g, as Lc from about -108 to 106. It's positive for dark
+/// text over a light background, negative otherwise.
+pub(crate) fn apca_contrast(text: Rgb, bg: Rgb) -> f64 {
+    const BLACK_THRESHOLD: f64 = 0.022;
+    const BLACK_CLAMP: f64 = 1.414;
+    const SCALE: f64 = 1.14;
+    const OFFSET: f64 = 0.027;
+    const MIN_DELTA_Y: f64 = 0.0005;
+    const MIN_CONTRAST: f64 = 0.1;
+
+    let luminance = |rgb: Rgb| {
+        let linear = |channel: u8| (channel as f64 / 255.0).powf(2.4);
+        let y = 0.2126729 * linear(rgb.r) + 0.7151522 * linear(rgb.g) + 0.0721750 * linear(rgb.b);
+        // Soft clamps the darkest colors, whose differences are hard to see.
+        if y < BLACK_THRESHOLD {
+            y + (BLACK_THRESHOLD - y).powf(BLACK_CLAMP)
+        } else {
+            y
+        }
+    };
+    let (text, bg) = (luminance(text), luminance(bg));
+    if (bg - text).abs() < MIN_DELTA_Y {
+        return 0.0;
+    }
+    let contrast = if bg > text {
+        let contrast = (bg.powf(0.56) - text.powf(0.57)) * SCALE;
+        if contrast < MIN_CONTRAST {
+            0.0
+        } else {
+            contrast - OFFSET
+        }
+    } else {
+        let contrast = (bg.powf(0.65) - text.powf(0.62)) * SCALE;
+        if contrast > -MIN_CONTRAST {
+            0.0
+        } else {
+            contrast + OFFSET
+        }
+    };
+    contrast * 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use alacritty_terminal::term::color::Rgb;
+
+    use super::{apca_contrast, wcag_ratio};
+
+    fn rgb(hex: &str) -> Rgb {
+        hex.parse().unwrap()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "{actual} isn't close to {expected}"
+        );
+    }
+
+    #[test]
+    fn wcag_ratios() {
+        assert_close(wcag_ratio(rgb("#000000"), rgb("#ffffff")), 21.0);
+        assert_close(wcag_ratio(rgb("#ffffff"), rgb("#000000")), 21.0);
+        assert_close(wcag_ratio(rgb("#777777"), rgb("#ffffff")), 4.48);
+        assert_close(wcag_ratio(rgb("#767676"), rgb("#ffffff")), 4.54);
+        assert_close(wcag_ratio(rgb("#123456"), rgb("#123456")), 1.0);
+    }
+
+    #[test]
+    fn apca_contrasts() {
+        assert_close(apca_contrast(rgb("#888888"), rgb("#ffffff")), 63.06);
+        assert_close(apca_contrast(rgb("#ffffff"), rgb("#888888")), -68.54);
+        assert_close(apca_contrast(rgb("#000000"), rgb("#aaaaaa")), 58.15);
+        assert_close(apca_contrast(rgb("#aaaaaa"), rgb("#000000")), -56.24);
+        assert_close(apca_contrast(rgb("#123456"), rgb("#123456")), 0.0);
+    }
+}
@@ -0,0 +1,77 @@
+use std::{io::BufWriter, path::Path, time::Duration};
+
+use alacritty_terminal::term::color::Rgb;
+use gif::{Encoder, Repeat};
+
+use super::{png::render, Animation, Exporter, Snapshot, Span};
+use crate::config::PngOptions;
+
+/// Speed of the quantization of frames with more than 256 colors, from 1 (best) to 30 (fastest).
+const QUANTIZATION_SPEED: i32 = 10;
+
+/// Writes snapshots and animations as GIFs rasterized the same way as PNGs.
+pub(crate) struct Gif {
+    options: PngOptions,
+}
+
+impl Gif {
+    pub(crate) fn new(options: PngOptions) -> Gif {
+        Gif { options }
+    }
+}
+
+impl Exporter for Gif {
+    fn export(&mut self, snapshot: &Snapshot<'_>) {
+        let frames = [(snapshot.lines, Duration::ZERO)];
+        export(
+            &snapshot.capture.out_dir,
+            snapshot.title,
+            &frames,
+            &self.options,
+            snapshot.capture.palette.background(),
+        );
+    }
+
+    fn export_animation(&mut self, animation: &Animation<'_>) {
+        let frames = animation
+            .frames
+            .iter()
+            .map(|frame| (frame.lines.as_slice(), frame.duration))
+            .collect::<Vec<_>>();
+        export(
+            &animation.capture.out_dir,
+            animation.title,
+            &frames,
+            &self.options,
+            animation.capture.palette.background(),
+        );
+    }
+}
+
+fn export(
+    out_dir: &Path,
+    title: &str,
+    frames: &[(&[Vec<Span>], Duration)],
+    options: &PngOptions,
+    background: Option<Rgb>,
+) {
+    let mut canvases = frames
+        .iter()
+        .map(|(lines, _)| render(lines, options, background))
+        .collect::<Vec<_>>();
+    let (width, height) = (canvases[0].width as u16, canvases[0].height as u16);
+    let file = BufWriter::new(super::create(out_dir, title, "gif"));
+    let mut encoder = Encoder::new(file, width, height, &[]).unwrap();
+    if frames.len() > 1 {
+        encoder.set_repeat(Repeat::Infinite).unwrap();
+    }
+    for (canvas, (_, duration)) in canvases.iter_mut().zip(frames) {
+        // Frames have their own palette, which is exact unless they have more than 256 colors.
+        // Pixels that aren't opaque at all are left transparent.
+        let mut frame =
+            gif::Frame::from_rgba_speed(width, height, &mut canvas.pixels, QUANTIZATION_SPEED);
+        // GIF delays are in hundredths of a second.
+        frame.delay = (duration.as_millis() / 10).min(u16::MAX as u128) as u16;
+        encoder.write_frame(&frame).unwrap();
+    }
+}
@@ -0,0 +1,116 @@
+use std::{collections::BTreeMap, io::Write, path::Path};
+
+use super::{escape, Capture, Exporter, Shape, Snapshot, Span};
+use crate::{config::HtmlStyles, fingerprint::fnv1a};
+
+pub(crate) struct Html {
+    /// Set when spans are styled with classes.
+    palette: Option<Palette>,
+}
+
+impl Html {
+    pub(crate) fn new(styles: HtmlStyles) -> Html {
+        Html {
+            palette: (styles == HtmlStyles::Classes).then(Palette::default),
+        }
+    }
+}
+
+impl Exporter for Html {
+    fn export(&mut self, snapshot: &Snapshot<'_>) {
+        export(
+            &snapshot.capture.out_dir,
+            snapshot.lines,
+            snapshot.title,
+            self.palette.as_mut(),
+        );
+    }
+
+    fn finish(&mut self, capture: &Capture<'_>) {
+        if let Some(palette) = &self.palette {
+            palette.export(&capture.out_dir);
+        }
+    }
+}
+
+/// Classes of the spans of a colorscheme's snapshots, written to a stylesheet shared by them.
+///
+/// Class names are derived from the styles they stand for, so the same style has the same class
+/// in every snapshot, colorscheme and run.
+#[derive(Default)]
+struct Palette {
+    rules: BTreeMap<String, String>,
+}
+
+impl Palette {
+    fn class(&mut self, declarations: String) -> String {
+        let class = format!("s{:012x}", fnv1a(declarations.as_bytes()) >> 16);
+        self.rules.entry(class.clone()).or_insert(declarations);
+        class
+    }
+
+    /// Writes `palette.css` to `out_dir`.
+    fn export(&self, out_dir: &Path) {
+        let mut file = super::create(out_dir, "palette", "css");
+        writeln!(file, ".term-nvim-screen .{WIDE_CLASS}{{{WIDE_CSS}}}").unwrap();
+        for (class, declarations) in &self.rules {
+            writeln!(file, ".term-nvim-screen .{class}{{{declarations}}}").unwrap();
+        }
+    }
+}
+
+const WIDE_CLASS: &str = "wide";
+/// Fonts rarely draw wide chars exactly two columns wide, this keeps the columns after them
+/// aligned.
+const WIDE_CSS: &str = "display:inline-block;width:2ch;";
+
+/// Writes the snapshot with a class per span style taken from `palette`, or with inline styles
+/// if there is no palette.
+fn export(out_dir: &Path, lines: &[Vec<Span>], title: &str, mut palette: Option<&mut Palette>) {
+    let mut file = super::create(out_dir, title, "html");
+    write!(file, "<pre class='term-nvim-screen'><code>").unwrap();
+    for line in lines {
+        for span in line {
+            let Span { bg, fg, .. } = span;
+            let mut text = String::new();
+            for cell in &span.cells {
+                if !cell.wide {
+                    text.push_str(&escape(&cell.text));
+                } else if palette.is_some() {
+                    text.push_str(&format!(
+                        "<span class='{WIDE_CLASS}'>{}</span>",
+                        escape(&cell.text)
+                    ));
+                } else {
+                    text.push_str(&format!(
+                        "<span style='{WIDE_CSS}'>{}</span>",
+                        escape(&cell.text)
+                    ));
+                }
+            }
+            let flags_css = span.flags_css();
+            let mut declarations = format!("background-color:{bg};color:{fg};{flags_css}");
+            if let Some(cursor) = &span.cursor {
+                let color = &cursor.color;
+                match cursor.shape {
+                    Shape::Block => {}
+                    Shape::Beam => {
+                        declarations.push_str(&format!("box-shadow:inset 2px 0 {color};"))
+                    }
+                    Shape::Underline => {
+                        declarations.push_str(&format!("box-shadow:inset 0 -2px {color};"))
+                    }
+                }
+            }
+            match palette.as_deref_mut() {
+                Some(palette) => {
+                    let class = palette.class(declarations);
+                    write!(file, "<span class='{class}'>{text}</span>").unwrap();
+                }
+                None => write!(file, "<span style='{declarations}'>{text}</span>").unwrap(),
+            }
+        }
+        writeln!(file).unwrap();
+    }
+    write!(file, "</code></pre>").unwrap();
+}
@@ -0,0 +1,181 @@
+//! Cell-level dump of snapshots for tools that need the colors back, written to `{title}.json`.
+//!
+//! Schema, version 1:
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "plugin": "io.github.folke.tokyonight-nvim",
+//!   "colorscheme": "tokyonight",
+//!   "background": "dark" | "light" | null,   // only set when capturing several backgrounds
+//!   "title": "Rust",
+//!   "columns": 110,
+//!   "lines": 40,
+//!   "cursor": {
+//!     "line": 0,                              // zero-based
+//!     "column": 0,
+//!     "shape": "block" | "beam" | "underline", // only set when the cursor is drawn
+//!     "color": "#c0caf5",                      // only set when the cursor is drawn
+//!     "textColor": "#1a1b26"                   // only set when the cursor is drawn
+//!   },
+//!   "cells": [                                // `lines` arrays of `columns` cells
+//!     [
+//!       {
+//!         "text": "f",        // char followed by its zero-width chars, "" for wide char spacers
+//!         "fg": "#c0caf5",    // colors as drawn, i.e. after applying inverse, dim and hidden
+//!         "bg": "#1a1b26",    // or "transparent" for the default background left transparent
+//!         "flags": ["bold"],  // any of the `Flag` names below
+//!         "hl": "@keyword"    // highlight group of the buffer text, omitted when unknown
+//!       }
+//!     ]
+//!   ]
+//! }
+//! ```
+//!
+//! Fields are only ever added within a version, so consumers should ignore the ones they don't
+//! know about.
+
+use std::{io::BufWriter, path::Path};
+
+use alacritty_terminal::{
+    grid::Dimensions,
+    index::{Column, Line},
+    term::cell::Flags,
+};
+use serde::Serialize;
+
+use super::{Exporter, Shape, Snapshot};
+use crate::config::Background;
+
+const VERSION: u32 = 1;
+
+pub(crate) struct Json;
+
+impl Exporter for Json {
+    fn export(&mut self, snapshot: &Snapshot<'_>) {
+        export(&snapshot.capture.out_dir, snapshot);
+    }
+
+    fn wants_highlights(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Serialize)]
+struct Dump<'a> {
+    version: u32,
+    plugin: &'a str,
+    colorscheme: &'a str,
+    background: Option<Background>,
+    title: &'a str,
+    columns: usize,
+    lines: usize,
+    cursor: Cursor<'a>,
+    cells: Vec<Vec<CellDump<'a>>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Cursor<'a> {
+    line: usize,
+    column: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shape: Option<Shape>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text_color: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct CellDump<'a> {
+    text: String,
+    fg: String,
+    bg: String,
+    flags: Vec<Flag>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hl: Option<&'a str>,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Flag {
+    Bold,
+    Italic,
+    Underline,
+    DoubleUnderline,
+    Undercurl,
+    DottedUnderline,
+    DashedUnderline,
+    Strikeout,
+    Inverse,
+    Dim,
+    Hidden,
+    WideChar,
+    WideCharSpacer,
+}
+
+const FLAGS: [(Flags, Flag); 13] = [
+    (Flags::BOLD, Flag::Bold),
+    (Flags::ITALIC, Flag::Italic),
+    (Flags::UNDERLINE, Flag::Underline),
+    (Flags::DOUBLE_UNDERLINE, Flag::DoubleUnderline),
+    (Flags::UNDERCURL, Flag::Undercurl),
+    (Flags::DOTTED_UNDERLINE, Flag::DottedUnderline),
+    (Flags::DASHED_UNDERLINE, Flag::DashedUnderline),
+    (Flags::STRIKEOUT, Flag::Strikeout),
+    (Flags::INVERSE, Flag::Inverse),
+    (Flags::DIM, Flag::Dim),
+    (Flags::HIDDEN, Flag::Hidden),
+    (Flags::WIDE_CHAR, Flag::WideChar),
+    (Flags::WIDE_CHAR_SPACER, Flag::WideCharSpacer),
+];
+
+fn export(out_dir: &Path, snapshot: &Snapshot<'_>) {
+    let grid = snapshot.grid;
+    let cells = (0..grid.screen_lines())
+        .map(|line| {
+            let row = &grid[Line(line as i32)];
+            (0..grid.columns())
+                .map(|col| {
+                    let cell = &row[Column(col)];
+                    let (fg, bg) = super::colors(cell, snapshot.capture.palette);
+                    CellDump {
+                        text: super::cell_text(cell).unwrap_or_default(),
+                        fg,
+                        bg,
+                        flags: FLAGS
+                            .iter()
+                            .filter(|(flags, _)| cell.flags.contains(*flags))
+                            .map(|&(_, flag)| flag)
+                            .collect(),
+                        hl: snapshot
+                            .highlights
+                            .and_then(|highlights| highlights.get(&(line, col)))
+                            .map(String::as_str),
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let cursor = grid.cursor.point;
+    let dump = Dump {
+        version: VERSION,
+        plugin: snapshot.capture.plugin_id,
+        colorscheme: snapshot.capture.colorscheme,
+        background: snapshot.capture.background,
+        title: snapshot.title,
+        columns: grid.columns(),
+        lines: grid.screen_lines(),
+        cursor: Cursor {
+            line: cursor.line.0.max(0) as usize,
+            column: cursor.column.0,
+            shape: snapshot.cursor.map(|cursor| cursor.shape),
+            color: snapshot.cursor.map(|cursor| cursor.color.as_str()),
+            text_color: snapshot.cursor.map(|cursor| cursor.text_color.as_str()),
+        },
+        cells,
+    };
+    let file = super::create(out_dir, snapshot.title, "json");
+    serde_json::to_writer(BufWriter::new(file), &dump).unwrap();
+}
@@ -0,0 +1,271 @@
+use std::{collections::HashMap, io::BufWriter, path::Path, sync::OnceLock};
+
+use alacritty_terminal::term::{cell::Flags, color::Rgb};
+use fontdue::{Font, FontSettings, Metrics};
+
+use super::{Exporter, Snapshot, Span};
+use crate::{color, config::PngOptions};
+
+/// Horizontal shift, per pixel over the baseline, of italic glyphs.
+const ITALIC_SLANT: f32 = 0.2;
+
+static FONT_BYTES: &[u8] = include_bytes!("../../site/public/monospace.ttf");
+
+fn font() -> &'static Font {
+    static FONT: OnceLock<Font> = OnceLock::new();
+    FONT.get_or_init(|| {
+        Font::from_bytes(FONT_BYTES, FontSettings::default()).expect("bundled font must be valid")
+    })
+}
+
+/// RGBA image the grid is drawn on.
+pub(crate) struct Canvas {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) pixels: Vec<u8>,
+}
+
+impl Canvas {
+    /// Canvas filled with `bg`, or transparent if there is none.
+    fn new(width: usize, height: usize, bg: Option<Rgb>) -> Canvas {
+        let pixel = bg.map_or([0; 4], |bg| [bg.r, bg.g, bg.b, 255]);
+        let pixels = pixel.repeat(width * height);
+        Canvas {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+        for y in y..(y + height).min(self.height) {
+            for x in x..(x + width).min(self.width) {
+                self.blend(x, y, color, 255);
+            }
+        }
+    }
+
+    /// Same as `blend` but does nothing if `(x, y)` is out of the canvas.
+    fn blend_at(&mut self, x: i32, y: i32, color: Rgb, coverage: u8) {
+        if coverage == 0 || x < 0 || y < 0 {
+            return;
+        }
+        let (x, y) = (x as usize, y as usize);
+        if x < self.width && y < self.height {
+            self.blend(x, y, color, coverage);
+        }
+    }
+
+    /// Draws `color` over the pixel at `(x, y)`, `coverage` being the opacity of `color`.
+    fn blend(&mut self, x: usize, y: usize, color: Rgb, coverage: u8) {
+        let i = (y * self.width + x) * 4;
+        let coverage = coverage as u32;
+        let alpha = self.pixels[i + 3] as u32;
+        // Opacity of the result, and the weight of the pixel's color in it, scaled by 255².
+        let pixel_weight = alpha * (255 - coverage);
+        let blended_alpha = coverage * 255 + pixel_weight;
+        if blended_alpha == 0 {
+            return;
+        }
+        for (channel, value) in [color.r, color.g, color.b].into_iter().enumerate() {
+            let pixel = &mut self.pixels[i + channel];
+            *pixel = ((value as u32 * coverage * 255 + *pixel as u32 * pixel_weight)
+                / blended_alpha) as u8;
+        }
+        self.pixels[i + 3] = (blended_alpha / 255) as u8;
+    }
+}
+
+pub(crate) struct Png {
+    options: PngOptions,
+}
+
+impl Png {
+    pub(crate) fn new(options: PngOptions) -> Png {
+        Png { options }
+    }
+}
+
+impl Exporter for Png {
+    fn export(&mut self, snapshot: &Snapshot<'_>) {
+        export(
+            &snapshot.capture.out_dir,
+            snapshot.lines,
+            snapshot.title,
+            &self.options,
+            snapshot.capture.palette.background(),
+        );
+    }
+}
+
+fn export(
+    out_dir: &Path,
+    lines: &[Vec<Span>],
+    title: &str,
+    options: &PngOptions,
+    background: Option<Rgb>,
+) {
+    let canvas = render(lines, options, background);
+    let file = super::create(out_dir, title, "png");
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        canvas.width as u32,
+        canvas.height as u32,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&canvas.pixels).unwrap();
+}
+
+/// Rasterizes `lines` with the bundled font, padded with `background` or transparent space.
+pub(crate) fn render(lines: &[Vec<Span>], options: &PngOptions, background: Option<Rgb>) -> Canvas {
+    let PngOptions {
+        cell_width,
+        cell_height,
+        font_size,
+        padding,
+    } = *options;
+    let font = font();
+    let size = super::size(lines);
+    let mut canvas = Canvas::new(
+        size.columns * cell_width + 2 * padding,
+        size.lines * cell_height + 2 * padding,
+        background,
+    );
+    let line_metrics = font
+        .horizontal_line_metrics(font_size)
+        .expect("bundled font must have horizontal metrics");
+    // Center the text vertically in its cell.
+    let baseline = ((cell_height as f32 - (line_metrics.ascent - line_metrics.descent)) / 2.0
+        + line_metrics.ascent)
+        .round() as i32;
+    let mut glyphs: HashMap<char, (Metrics, Vec<u8>)> = HashMap::new();
+
+    for (line, spans) in lines.iter().enumerate() {
+        let y = padding + line * cell_height;
+        for span in spans {
+            let x = padding + span.column * cell_width;
+            let fg = super::rgb(&span.fg);
+            // The canvas is transparent wherever nothing is drawn.
+            if span.bg != color::TRANSPARENT {
+                let bg = super::rgb(&span.bg);
+                canvas.fill(x, y, span.width * cell_width, cell_height, bg);
+            }
+            let mut glyph_x = x as i32;
+            for cell in &span.cells {
+                for (i, ch) in cell.text.chars().enumerate() {
+                    if ch == ' ' {
+                        continue;
+                    }
+                    let (metrics, bitmap) = glyphs
+                        .entry(ch)
+                        .or_insert_with(|| font.rasterize(ch, font_size));
+                    // Zero-width chars are drawn over the char they follow. Fonts that don't
+                    // advance over combining marks expect them to be drawn after that char.
+                    let origin_x = if i > 0 && metrics.advance_width < 0.5 {
+                        glyph_x + cell_width as i32
+                    } else {
+                        glyph_x
+                    };
+                    draw_glyph(
+                        &mut canvas,
+                        metrics,
+                        bitmap,
+                        (origin_x, y as i32 + baseline),
+                        fg,
+                        span.flags,
+                    );
+                }
+                glyph_x += if cell.wide { 2 } else { 1 } * cell_width as i32;
+            }
+            draw_lines(
+                &mut canvas,
+                span,
+                (x as i32, y as i32 + baseline),
+                options,
+                fg,
+            );
+            if let Some(cursor) = &span.cursor {
+                if let Some([dx, dy, width, height]) = cursor.bar(cell_width, cell_height) {
+                    canvas.fill(x + dx, y + dy, width, height, super::rgb(&cursor.color));
+                }
+            }
+        }
+    }
+    canvas
+}
+
+/// Draws a glyph whose origin is at `(x, baseline)`. Bold and italic are faked since the font
+/// has a single style.
+fn draw_glyph(
+    canvas: &mut Canvas,
+    metrics: &Metrics,
+    bitmap: &[u8],
+    (x, baseline): (i32, i32),
+    fg: Rgb,
+    flags: Flags,
+) {
+    let top = baseline - metrics.height as i32 - metrics.ymin;
+    let boldness = if flags.contains(Flags::BOLD) { 2 } else { 1 };
+    for (row, coverages) in bitmap.chunks(metrics.width.max(1)).enumerate() {
+        let y = top + row as i32;
+        // Slant italic glyphs by shifting every row proportionally to its height over the
+        // baseline.
+        let slant = if flags.contains(Flags::ITALIC) {
+            ((baseline - y) as f32 * ITALIC_SLANT).round() as i32
+        } else {
+            0
+        };
+        for (col, &coverage) in coverages.iter().enumerate() {
+            for offset in 0..boldness {
+                let glyph_x = x + metrics.xmin + col as i32 + slant + offset;
+                canvas.blend_at(glyph_x, y, fg, coverage);
+            }
+        }
+    }
+}
+
+/// Draws the underlines and strikethrough of `span`, which starts at `(x, baseline)`.
+fn draw_lines(
+    canvas: &mut Canvas,
+    span: &Span,
+    (x, baseline): (i32, i32),
+    options: &PngOptions,
+    fg: Rgb,
+) {
+    let width = (span.width * options.cell_width) as i32;
+    let underline_color = span.underline_color.as_deref().map_or(fg, super::rgb);
+    let underline_y = baseline + 2;
+    for dx in 0..width {
+        let underline_dys: &[i32] = if span.flags.contains(Flags::UNDERCURL) {
+            let phase = dx as f32 / options.cell_width as f32 * std::f32::consts::TAU;
+            &[1 + (phase.sin() * 1.5).round() as i32]
+        } else if span.flags.contains(Flags::DOUBLE_UNDERLINE) {
+            &[0, 2]
+        } else if span.flags.contains(Flags::DOTTED_UNDERLINE) {
+            if dx % 2 == 0 {
+                &[0]
+            } else {
+                &[]
+            }
+        } else if span.flags.contains(Flags::DASHED_UNDERLINE) {
+            if dx % 6 < 4 {
+                &[0]
+            } else {
+                &[]
+            }
+        } else if span.flags.contains(Flags::UNDERLINE) {
+            &[0]
+        } else {
+            &[]
+        };
+        for dy in underline_dys {
+            canvas.blend_at(x + dx, underline_y + dy, underline_color, 255);
+        }
+        if span.flags.contains(Flags::STRIKEOUT) {
+            let strikeout_y = baseline - (options.font_size * 0.3).round() as i32;
+            canvas.blend_at(x + dx, strikeout_y, fg, 255);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::{io::Write, path::Path};
+
+use alacritty_terminal::term::cell::Flags;
+
+use super::{escape, Animation, Exporter, Frame, Snapshot, Span};
+use crate::{color, config::Size};
+
+const FONT_SIZE: usize = 15;
+/// Most monospace fonts advance 0.6em per char.
+const CELL_WIDTH: usize = 9;
+const CELL_HEIGHT: usize = 18;
+/// Distance from the top of a cell to the baseline of its text.
+const BASELINE: usize = 14;
+
+pub(crate) struct Svg;
+
+impl Exporter for Svg {
+    fn export(&mut self, snapshot: &Snapshot<'_>) {
+        export(&snapshot.capture.out_dir, snapshot.lines, snapshot.title);
+    }
+
+    fn export_animation(&mut self, animation: &Animation<'_>) {
+        export_animation(
+            &animation.capture.out_dir,
+            animation.frames,
+            animation.title,
+        );
+    }
+}
+
+fn export(out_dir: &Path, lines: &[Vec<Span>], title: &str) {
+    let mut file = super::create(out_dir, title, "svg");
+    write_header(&mut file, super::size(lines));
+    write_screen(&mut file, lines);
+    writeln!(file, "</svg>").unwrap();
+}
+
+/// Writes every frame in a group that a looping CSS animation only shows during the frame's
+/// share of the total duration.
+fn export_animation(out_dir: &Path, frames: &[Frame], title: &str) {
+    let mut file = super::create(out_dir, title, "svg");
+    // Resizing is forbidden during animations, so every frame has the same size.
+    write_header(&mut file, super::size(&frames[0].lines));
+    write_animation_style(&mut file, frames);
+    for (i, frame) in frames.iter().enumerate() {
+        writeln!(file, "<g id='frame{i}' class='frame'>").unwrap();
+        write_screen(&mut file, &frame.lines);
+        writeln!(file, "</g>").unwrap();
+    }
+    writeln!(file, "</svg>").unwrap();
+}
+
+/// Writes the style that shows every frame, in a group with the id `frame{i}`, during its share
+/// of the total duration.
+fn write_animation_style(file: &mut impl Write, frames: &[Frame]) {
+    let total = frames
+        .iter()
+        .map(|frame| frame.duration.as_secs_f64())
+        .sum::<f64>()
+        .max(0.001);
+    writeln!(file, "<style>").unwrap();
+    writeln!(
+        file,
+        ".frame{{visibility:hidden;animation:{total:.3}s step-end infinite}}"
+    )
+    .unwrap();
+    let mut start = 0.0;
+    for (i, frame) in frames.iter().enumerate() {
+        let end = start + frame.duration.as_secs_f64();
+        let (start_percent, end_percent) = (start / total * 100.0, end / total * 100.0);
+        writeln!(
+            file,
+            "#frame{i}{{animation-name:frame{i}}}\
+             @keyframes frame{i}{{0%{{visibility:hidden}}{start_percent:.3}%{{visibility:visible}}\
+             {end_percent:.3}%{{visibility:hidden}}}}"
+        )
+        .unwrap();
+        start = end;
+    }
+    writeln!(file, "</style>").unwrap();
+}
+
+fn write_header(file: &mut impl Write, size: Size) {
+    let width = size.columns * CELL_WIDTH;
+    let height = size.lines * CELL_HEIGHT;
+    writeln!(
+        file,
+        "<svg xmlns='http://www.w3.org/2000/svg' width='{width}' height='{height}' \
+         viewBox='0 0 {width} {height}' font-family='monospace' font-size='{FONT_SIZE}'>"
+    )
+    .unwrap();
+}
+
+fn write_screen(file: &mut impl Write, lines: &[Vec<Span>]) {
+    writeln!(file, "<g shape-rendering='crispEdges'>").unwrap();
+    for (line, spans) in lines.iter().enumerate() {
+        let y = line * CELL_HEIGHT;
+        for span in spans {
+            let x = span.column * CELL_WIDTH;
+            let width = span.width * CELL_WIDTH;
+            let bg = &span.bg;
+            if bg != color::TRANSPARENT {
+                writeln!(
+                    file,
+                    "<rect x='{x}' y='{y}' width='{width}' height='{CELL_HEIGHT}' fill='{bg}'/>"
+                )
+                .unwrap();
+            }
+            if let Some(cursor) = &span.cursor {
+                if let Some([dx, dy, width, height]) = cursor.bar(CELL_WIDTH, CELL_HEIGHT) {
+                    let (x, y, color) = (x + dx, y + dy, &cursor.color);
+                    writeln!(
+                        file,
+                        "<rect x='{x}' y='{y}' width='{width}' height='{height}' fill='{color}'/>"
+                    )
+                    .unwrap();
+                }
+            }
+        }
+    }
+    writeln!(file, "</g>").unwrap();
+
+    // Text is stretched to the width of its cells so that columns stay aligned whatever the
+    // font ends up being.
+    writeln!(file, "<g xml:space='preserve'>").unwrap();
+    for (line, spans) in lines.iter().enumerate() {
+        let y = line * CELL_HEIGHT + BASELINE;
+        for span in spans {
+            let has_lines = span
+                .flags
+                .intersects(Flags::ALL_UNDERLINES | Flags::STRIKEOUT);
+            let text = span.text();
+            if text.trim().is_empty() && !has_lines {
+                continue;
+            }
+            let x = span.column * CELL_WIDTH;
+            let width = span.width * CELL_WIDTH;
+            let fg = &span.fg;
+            let text = escape(&text);
+            let flags_css = span.flags_css();
+            let style = if flags_css.is_empty() {
+                String::new()
+            } else {
+                format!(" style='{flags_css}'")
+            };
+            writeln!(
+                file,
+                "<text x='{x}' y='{y}' fill='{fg}' textLength='{width}' \
+                 lengthAdjust='spacingAndGlyphs'{style}>{text}</text>"
+            )
+            .unwrap();
+        }
+    }
+    writeln!(file, "</g>").unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::write_animation_style;
+    use crate::export::Frame;
+
+    fn style(durations_ms: &[u64]) -> Vec<String> {
+        let frames = durations_ms
+            .iter()
+            .map(|&ms| Frame {
+                lines: Vec::new(),
+                duration: Duration::from_millis(ms),
+            })
+            .collect::<Vec<_>>();
+        let mut style = Vec::new();
+        write_animation_style(&mut style, &frames);
+        String::from_utf8(style)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn keyframes(i: usize, start: &str, end: &str) -> String {
+        format!(
+            "#frame{i}{{animation-name:frame{i}}}@keyframes frame{i}{{0%{{visibility:hidden}}\
+             {start}%{{visibility:visible}}{end}%{{visibility:hidden}}}}"
+        )
+    }
+
+    #[test]
+    fn two_frames_split_the_animation() {
+        assert_eq!(
+            style(&[500, 500]),
+            [
+                "<style>".to_string(),
+                ".frame{visibility:hidden;animation:1.000s step-end infinite}".to_string(),
+                keyframes(0, "0.000", "50.000"),
+                keyframes(1, "50.000", "100.000"),
+                "</style>".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn three_frames_follow_their_durations() {
+        assert_eq!(
+            style(&[100, 200, 300]),
+            [
+                "<style>".to_string(),
+                ".frame{visibility:hidden;animation:0.600s step-end infinite}".to_string(),
+                keyframes(0, "0.000", "16.667"),
+                keyframes(1, "16.667", "50.000"),
+                keyframes(2, "50.000", "100.000"),
+                "</style>".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Fingerprints of the inputs of captures, so that a run can skip the captures whose inputs
+//! haven't changed since the previous one.
+
+use std::fmt::Debug;
+
+use include_dir::Dir;
+
+/// 64-bit FNV-1a, used instead of `DefaultHasher` whose output may change between releases.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Fingerprint {
+    /// Every input, each prefixed with its length so that moving bytes from one input to the
+    /// next changes the fingerprint.
+    bytes: Vec<u8>,
+}
+
+impl Fingerprint {
+    pub(crate) fn add(&mut self, input: &[u8]) -> &mut Fingerprint {
+        self.bytes
+            .extend_from_slice(&(input.len() as u64).to_le_bytes());
+        self.bytes.extend_from_slice(input);
+        self
+    }
+
+    /// Adds the `Debug` representation of `input`, which changes whenever its value does for
+    /// the types used in configs and scenarios.
+    pub(crate) fn add_debug(&mut self, input: &impl Debug) -> &mut Fingerprint {
+        self.add(format!("{input:?}").as_bytes())
+    }
+
+    /// Adds the path and contents of every file in `dir`.
+    pub(crate) fn add_dir(&mut self, dir: &Dir<'_>) -> &mut Fingerprint {
+        for file in dir.files() {
+            self.add(file.path().to_string_lossy().as_bytes());
+            self.add(file.contents());
+        }
+        for dir in dir.dirs() {
+            self.add_dir(dir);
+        }
+        self
+    }
+
+    pub(crate) fn finish(&self) -> String {
+        format!("{:016x}", fnv1a(&self.bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fnv1a, Fingerprint};
+    use crate::SAMPLES_DIR;
+
+    fn fingerprint(inputs: &[&[u8]]) -> String {
+        let mut fingerprint = Fingerprint::default();
+        for input in inputs {
+            fingerprint.add(input);
+        }
+        fingerprint.finish()
+    }
+
+    #[test]
+    fn fnv1a_matches_reference_values() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn fingerprints_are_stable() {
+        assert_eq!(fingerprint(&[]), "cbf29ce484222325");
+        assert_eq!(
+            fingerprint(&[b"v0.9.5", b"tokyonight"]),
+            fingerprint(&[b"v0.9.5", b"tokyonight"])
+        );
+        let samples = || Fingerprint::default().add_dir(&SAMPLES_DIR).finish();
+        assert_eq!(samples(), samples());
+    }
+
+    #[test]
+    fn fingerprints_change_with_inputs() {
+        let base = fingerprint(&[b"v0.9.5", b"tokyonight"]);
+        assert_ne!(base, fingerprint(&[b"v0.9.5", b"tokyonight-day"]));
+        assert_ne!(base, fingerprint(&[b"tokyonight", b"v0.9.5"]));
+        assert_ne!(base, fingerprint(&[b"v0.9.5", b"tokyonight", b""]));
+        // Moving bytes from one input to the next changes the fingerprint.
+        assert_ne!(base, fingerprint(&[b"v0.9.5t", b"okyonight"]));
+        assert_ne!(
+            Fingerprint::default().add_debug(&Some(1)).finish(),
+            Fingerprint::default().add_debug(&Some(2)).finish()
+        );
+    }
+}
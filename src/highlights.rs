@@ -0,0 +1,57 @@
+//! Every highlight group of a colorscheme, written to `highlights.json` next to its screens.
+//!
+//! Schema, version 1:
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "colorscheme": "tokyonight",
+//!   "background": "dark",            // value of `background` once the colorscheme is loaded
+//!   "groups": {                      // every group nvim knows about, sorted by name
+//!     "@keyword": {
+//!       "link": "Keyword",           // group the definition links to, omitted if not a link
+//!       "fg": "#9d7cd8",             // colors and attributes with every link followed,
+//!       "bg": "#1a1b26",             // omitted when unset
+//!       "sp": "#db4b4b",
+//!       "attributes": ["italic"],    // any of bold, italic, underline, undercurl, underdouble,
+//!                                    // underdotted, underdashed, strikethrough, reverse,
+//!                                    // standout, nocombine and altfont
+//!       "blend": 10
+//!     }
+//!   }
+//! }
+//! ```
+
+use std::{collections::BTreeMap, io::BufWriter, path::Path};
+
+use serde::Serialize;
+
+use crate::nvim::Highlight;
+
+const VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Highlights<'a> {
+    version: u32,
+    colorscheme: &'a str,
+    background: &'a str,
+    groups: &'a BTreeMap<String, Highlight>,
+}
+
+/// Writes `highlights.json` to `out_dir`.
+pub(crate) fn export(
+    out_dir: &Path,
+    colorscheme: &str,
+    background: &str,
+    groups: &BTreeMap<String, Highlight>,
+) {
+    std::fs::create_dir_all(out_dir).unwrap();
+    let file = std::fs::File::create(out_dir.join("highlights.json")).unwrap();
+    let highlights = Highlights {
+        version: VERSION,
+        colorscheme,
+        background,
+        groups,
+    };
+    serde_json::to_writer_pretty(BufWriter::new(file), &highlights).unwrap();
+}
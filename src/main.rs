@@ -4,71 +4,56 @@
 extern crate tracing;
 
 use std::{
-    io::Read,
+    collections::BTreeMap,
     io::Write,
+    io::{BufReader, Read},
     ops::DerefMut,
-    path::Path,
+    path::{Path, PathBuf},
     sync::{Arc, Condvar, Mutex},
     time::Duration,
 };
 
-use alacritty_terminal::{
-    index::{Column, Line},
-    term::cell::Cell,
-    Grid,
-};
+use alacritty_terminal::{event::VoidListener, grid::Dimensions, term::cell::Cell, Grid, Term};
+use asciicast::Recording;
+use color::Palette;
+use config::{Background, HtmlStyles, Size};
+use export::{Capture, Exporter, Format};
+use eyre::bail;
+use fingerprint::Fingerprint;
 use include_dir::{include_dir, Dir};
+use manifest::{CaptureEntry, ColorschemeEntry, Kind, Manifest, PluginEntry, Screen};
+use scenario::Action;
 use tracing_subscriber::EnvFilter;
+use variants::Variant;
 
 mod args;
+mod asciicast;
 mod color;
 mod config;
+mod export;
+mod fingerprint;
+mod highlights;
+mod manifest;
 mod nvim;
+mod scenario;
+mod screen;
+mod variants;
 mod vte;
 
 static SAMPLES_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/samples");
 static SAMPLES_EXTRACTION_PATH: &str = "/tmp/_$:$_samples";
 
-const COLUMNS: usize = 110;
-const LINES: usize = 40;
-
-#[derive(Debug)]
-enum Action {
-    NvimCmd(String),
-    Snapshot { title: String },
-}
-
-#[derive(Debug)]
-struct Span {
-    bg: String,
-    fg: String,
-    text: String,
-}
+/// Path in the temp dir that no other test uses.
+#[cfg(test)]
+fn temp_path(name: &str) -> PathBuf {
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
-fn actions(colorscheme: &str) -> Vec<Action> {
-    vec![
-        Action::NvimCmd(format!(":tabnew<CR>")),
-        Action::NvimCmd(format!(":-tabclose<CR>")),
-        Action::NvimCmd(format!(":colorscheme {colorscheme}<CR>")),
-        Action::NvimCmd(format!(":e sample.rs<CR>")),
-        Action::Snapshot {
-            title: "Rust".to_string(),
-        },
-        Action::NvimCmd(format!(":e sample.tsx<CR>")),
-        Action::Snapshot {
-            title: "Typescript".to_string(),
-        },
-        Action::NvimCmd(format!(":e sample_diff_0.rs | diffthis<CR>")),
-        Action::NvimCmd(format!(":vert new sample_diff_1.rs | diffthis<CR>")),
-        Action::Snapshot {
-            title: "Diff".to_string(),
-        },
-        Action::NvimCmd(format!(":Telescope find_files<CR>")),
-        Action::Snapshot {
-            title: "Telescope".to_string(),
-        },
-        Action::NvimCmd(format!(":q<CR>")),
-    ]
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    std::env::temp_dir().join(format!(
+        "{name}-{}-{}",
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed)
+    ))
 }
 
 fn extract_samples() {
@@ -79,52 +64,171 @@ fn extract_samples() {
     SAMPLES_DIR.extract(SAMPLES_EXTRACTION_PATH).unwrap();
 }
 
-fn export_snapshot(out_dir: &Path, grid: &Grid<Cell>, title: &str) {
-    std::fs::create_dir_all(out_dir).unwrap();
-    let mut file = std::fs::OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(std::path::Path::new(out_dir).join(&format!("{title}.html")))
-        .unwrap();
-    let mut lines: Vec<Vec<Span>> = Vec::new();
-    for line in 0..LINES {
-        let mut line_spans: Vec<Span> = Vec::new();
-        let row = &grid[Line(line as i32)];
-        for col in 0..COLUMNS {
-            let cell = &row[Column(col)];
-            let ch = match cell.c {
-                '<' => "&lt;".to_string(),
-                '>' => "&gt;".to_string(),
-                '"' => "&quot;".to_string(),
-                ch => ch.to_string(),
-            };
-            let bg = color::to_string(cell.bg);
-            let fg = color::to_string(cell.fg);
-            let merge_with_last_span = line_spans
-                .last_mut()
-                .map_or(false, |span| span.bg == bg && span.fg == fg);
-            if merge_with_last_span {
-                line_spans.last_mut().unwrap().text.push_str(&ch);
-            } else {
-                line_spans.push(Span { bg, fg, text: ch });
-            }
+/// Blocks until nvim hasn't written anything for a while, i.e. it's done redrawing.
+fn wait_for_idle(read_byte_sync: &(Mutex<bool>, Condvar)) {
+    loop {
+        std::io::stdout().flush().unwrap();
+        // Wait for neovim's stdout or for N millis since we read neovim's stdout
+        let is_stdout_exhausted = {
+            let (read_byte, read_byte_cvar) = read_byte_sync;
+            let guard = read_byte.lock().unwrap();
+            let mut res = read_byte_cvar
+                .wait_timeout_while(guard, Duration::from_millis(100), |&mut read_byte| {
+                    !read_byte
+                })
+                .unwrap();
+            *res.0 = false;
+            res.1.timed_out()
+        };
+
+        // We have read all of neovim's stdout so far so we can proceed executing the actions
+        if is_stdout_exhausted {
+            break;
+        }
+    }
+}
+
+/// Copies the screen of `term`, with its cursor if `capture` shows it.
+fn screen(
+    term: &Mutex<Term<VoidListener>>,
+    capture: &Capture<'_>,
+) -> (Grid<Cell>, Option<export::Cursor>) {
+    let term = term.lock().unwrap();
+    let cursor = capture
+        .cursor
+        .then(|| export::cursor(&term, capture.palette))
+        .flatten();
+    (term.grid().clone(), cursor)
+}
+
+/// Resizes both the emulated terminal and nvim, unless they already have `size`.
+fn resize(term: &Mutex<Term<VoidListener>>, size: Size) -> eyre::Result<()> {
+    {
+        let mut term = term.lock().unwrap();
+        if term.columns() == size.columns && term.screen_lines() == size.lines {
+            return Ok(());
         }
-        lines.push(line_spans);
+        term.resize(vte::term_size(size));
     }
-    write!(file, "<pre class='term-nvim-screen'><code>").unwrap();
-    for line in &lines {
-        for span in line {
-            let Span { bg, fg, text } = span;
-            write!(
-                file,
-                "<span style='background-color:{bg};color:{fg};'>{text}</span>"
-            )
-            .unwrap();
+    nvim::resize(size)
+}
+
+/// Runs `steps`, adding the screens they take to `screens`, until one of them fails.
+fn run_steps(
+    term: &Mutex<Term<VoidListener>>,
+    read_byte_sync: &(Mutex<bool>, Condvar),
+    recording: &Mutex<Option<Recording>>,
+    steps: &[Action],
+    capture: &Capture<'_>,
+    exporters: &mut [Box<dyn Exporter>],
+    screens: &mut Vec<Screen>,
+) -> eyre::Result<()> {
+    // Title and frames of the current animation.
+    let mut animation = None;
+    let mut run = || -> eyre::Result<()> {
+        for action in steps {
+            match action.interpolate(capture.colorscheme) {
+                Action::NvimCmd(cmd) => {
+                    nvim::send_command(&cmd);
+                }
+                Action::TypeKeys(type_keys) => {
+                    nvim::type_keys(&type_keys.keys, Duration::from_millis(type_keys.delay_ms));
+                }
+                Action::Lua(lua) => {
+                    let result = nvim::eval_lua(&lua.code)?;
+                    if let Some(expected) = lua.expect {
+                        if result != expected {
+                            bail!(
+                                "lua step returned {result} instead of {expected}:\n{}",
+                                lua.code
+                            );
+                        }
+                    }
+                }
+                Action::WaitFor(wait) => {
+                    screen::wait_for(term, &wait, capture.palette)?;
+                }
+                Action::Snapshot { title } => {
+                    wait_for_idle(read_byte_sync);
+                    let (grid, cursor) = screen(term, capture);
+                    let highlights = if exporters.iter().any(|exporter| exporter.wants_highlights())
+                    {
+                        Some(nvim::screen_highlights()?)
+                    } else {
+                        None
+                    };
+                    export::snapshot(
+                        exporters,
+                        capture,
+                        &grid,
+                        cursor.as_ref(),
+                        &title,
+                        highlights.as_ref(),
+                    );
+                    screens.push(Screen {
+                        title,
+                        kind: Kind::Snapshot,
+                    });
+                }
+                Action::StartRecording { title } => {
+                    wait_for_idle(read_byte_sync);
+                    // Holding the terminal keeps the reader thread from processing output that
+                    // would be missing from both the first frame and the recording.
+                    let term = term.lock().unwrap();
+                    let screen = export::ansi_screen(term.grid(), capture.palette);
+                    let size = Size {
+                        columns: term.columns(),
+                        lines: term.screen_lines(),
+                    };
+                    *recording.lock().unwrap() = Some(Recording::new(title, screen, size));
+                }
+                Action::StopRecording => {
+                    wait_for_idle(read_byte_sync);
+                    let finished = recording.lock().unwrap().take();
+                    if let Some(finished) = finished {
+                        finished.export(&capture.out_dir);
+                        screens.push(Screen {
+                            title: finished.title().to_string(),
+                            kind: Kind::Recording,
+                        });
+                    }
+                }
+                Action::StartAnimation { title } => {
+                    animation = Some((title, Vec::new()));
+                }
+                Action::Frame { duration_ms } => {
+                    wait_for_idle(read_byte_sync);
+                    let (grid, cursor) = screen(term, capture);
+                    if let Some((_, frames)) = animation.as_mut() {
+                        frames.push(export::Frame {
+                            lines: export::spans(&grid, cursor.as_ref(), capture.palette),
+                            duration: Duration::from_millis(duration_ms),
+                        });
+                    }
+                }
+                Action::StopAnimation => {
+                    if let Some((title, frames)) = animation.take() {
+                        export::animation(exporters, capture, &title, &frames);
+                        screens.push(Screen {
+                            title,
+                            kind: Kind::Animation,
+                        });
+                    }
+                }
+                Action::Resize(size) => {
+                    resize(term, size)?;
+                }
+            }
         }
-        write!(file, "\n").unwrap();
+        Ok(())
+    };
+    let result = run();
+    // A failed step may have left a recording running.
+    recording.lock().unwrap().take();
+    for exporter in exporters.iter_mut() {
+        exporter.finish(capture);
     }
-    write!(file, "</code></pre>").unwrap();
+    result
 }
 
 fn main() {
@@ -136,27 +240,50 @@ fn main() {
     color_eyre::install().unwrap();
     let args = args::parse();
     let config = config::load(Path::new(&args.config));
+    let scenarios = match args
+        .scenarios
+        .as_deref()
+        .map(Path::new)
+        .or(config.scenarios.as_deref())
+    {
+        Some(path) => scenario::load(path).unwrap(),
+        None => scenario::builtin(),
+    };
+    let scenarios = scenario::select(scenarios, &args.scenario).unwrap();
+    let palette = Palette::load(&config.palette).unwrap();
+    let size = args.size.unwrap_or(config.size);
+    let plugin_steps = config
+        .plugins
+        .iter()
+        .map(|plugin| scenario::plugin_steps(&scenarios, plugin, size))
+        .collect::<eyre::Result<Vec<_>>>()
+        .unwrap();
     nvim::setup(&config.plugins);
     extract_samples();
-    let term = Arc::new(Mutex::new(vte::term()));
+    let term = Arc::new(Mutex::new(vte::term(size)));
     let mut vte_processor = vte::processor();
-    let mut nvim_process = nvim::spawn();
+    let mut nvim_process = nvim::spawn(size);
     // wait for nvim to start
     std::thread::sleep(Duration::from_millis(500));
     let nvim_stdout = nvim_process.stdout.take().unwrap();
     let read_byte_sync = Arc::new((Mutex::new(false), Condvar::new()));
+    let recording: Arc<Mutex<Option<Recording>>> = Arc::new(Mutex::new(None));
 
     // Read neovim's stdout on another thread.
     std::thread::spawn({
         let term = term.clone();
         let read_byte_sync = Arc::clone(&read_byte_sync);
+        let recording = Arc::clone(&recording);
 
         move || {
             let (read_byte, read_byte_cvar) = &*read_byte_sync;
-            for byte in nvim_stdout.bytes() {
+            for byte in BufReader::new(nvim_stdout).bytes() {
                 let mut term = term.lock().unwrap();
                 let byte = byte.unwrap();
                 vte_processor.advance(term.deref_mut(), byte);
+                if let Some(recording) = recording.lock().unwrap().as_mut() {
+                    recording.push(byte);
+                }
                 // Send a notification so that the main thread knows
                 // that neovim's hasn't finished rendering yet.
                 *read_byte.lock().unwrap() = true;
@@ -166,52 +293,173 @@ fn main() {
     });
     let read_byte_sync = Arc::clone(&read_byte_sync);
 
-    for plugin in &config.plugins {
+    let stylesheet =
+        args.formats.contains(&Format::Html) && config.html_styles == HtmlStyles::Classes;
+    let nvim_version = nvim::version().unwrap();
+    let out_root = Path::new(&args.out_dir);
+    let previous = if args.force {
+        None
+    } else {
+        Manifest::load(out_root)
+    };
+    let commits = nvim::plugin_commits().unwrap_or_else(|err| {
+        warn!("every colorscheme will be captured, plugin commits are unknown: {err:?}");
+        BTreeMap::new()
+    });
+    let mut inputs = Fingerprint::default();
+    inputs
+        .add(env!("CARGO_PKG_VERSION").as_bytes())
+        .add(nvim_version.as_bytes())
+        // Only the parts of init.lua every colorscheme has, so that changing the plugins of the
+        // config only changes the fingerprints of their own colorschemes.
+        .add(nvim::DEFAULT_PLUGINS.as_bytes())
+        .add(nvim::OPTIONS.as_bytes())
+        .add_debug(
+            &nvim::default_plugin_names()
+                .into_iter()
+                .map(|name| (name, commits.get(name)))
+                .collect::<Vec<_>>(),
+        )
+        .add_dir(&SAMPLES_DIR)
+        .add_debug(&size)
+        .add_debug(&(
+            &args.formats,
+            &config.backgrounds,
+            &config.png,
+            &config.html_styles,
+            config.cursor,
+            &palette,
+            &config.contrast,
+        ));
+    let mut manifest = Manifest::new(nvim_version, size);
+    for (plugin, steps) in config.plugins.iter().zip(&plugin_steps) {
+        let mut colorschemes = Vec::new();
         for colorscheme in &plugin.colorschemes {
-            for action in actions(&colorscheme) {
-                let out_dir = Path::new(&args.out_dir).join(&plugin.id).join(colorscheme);
-                match action {
-                    Action::NvimCmd(cmd) => {
-                        nvim::send_command(&cmd);
-                    }
-                    Action::Snapshot { title } => {
-                        loop {
-                            std::io::stdout().flush().unwrap();
-                            // Wait for neovim's stdout or for N millis since we read neovim's stdout
-                            let is_stdout_exhausted = {
-                                let (read_byte, read_byte_cvar) = &*read_byte_sync;
-                                let guard = read_byte.lock().unwrap();
-                                let mut res = read_byte_cvar
-                                    .wait_timeout_while(
-                                        guard,
-                                        Duration::from_millis(100),
-                                        |&mut read_byte| !read_byte,
-                                    )
-                                    .unwrap();
-                                *res.0 = false;
-                                res.1.timed_out()
-                            };
-
-                            // We have read all of neovim's stdout so far so we can proceed executing the actions
-                            if is_stdout_exhausted {
-                                let grid = {
-                                    let term = term.lock().unwrap();
-                                    term.grid().clone()
-                                };
-                                export_snapshot(&out_dir, &grid, &title);
-                                break;
-                            } else {
-                                // if stdout wasn't exhausted then try again
-                                continue;
-                            }
-                        }
-                    }
-                }
+            let out_dir = out_root.join(&plugin.id).join(colorscheme);
+            let transparent_background = plugin
+                .transparent_background
+                .unwrap_or(config.transparent_background);
+            let fingerprint = commits.get(plugin.lazy_name()).map(|commit| {
+                inputs
+                    .clone()
+                    .add(plugin.url.as_bytes())
+                    .add(commit.as_bytes())
+                    .add(colorscheme.as_bytes())
+                    .add_debug(steps)
+                    .add_debug(&transparent_background)
+                    .finish()
+            });
+            let unchanged = fingerprint.as_deref().and_then(|fingerprint| {
+                previous
+                    .as_ref()?
+                    .unchanged(out_root, &plugin.id, colorscheme, fingerprint)
+            });
+            if let Some(entry) = unchanged {
+                info!(
+                    plugin = plugin.id,
+                    colorscheme, "skipped unchanged colorscheme"
+                );
+                colorschemes.push(entry.clone());
+                continue;
             }
+            let captured_at = manifest::now();
+            let run_capture = |out_dir: PathBuf, background: Option<Background>| {
+                let mut screens = Vec::new();
+                // Normal as the colorscheme sets it, before the steps get to change it.
+                let mut normal = None;
+                let result = nvim::load_colorscheme(colorscheme, background).and_then(|colors| {
+                    let groups = nvim::highlights()?;
+                    highlights::export(&out_dir, colorscheme, &colors.normal.background, &groups);
+                    normal = Some(colors.normal.clone());
+                    // Cells without an RGB color take the colors the colorscheme gives the
+                    // terminal.
+                    let palette = palette.with_terminal_colors(&colors, transparent_background);
+                    let capture = Capture {
+                        out_dir: out_dir.clone(),
+                        plugin_id: &plugin.id,
+                        colorscheme,
+                        background,
+                        cursor: config.cursor,
+                        palette: &palette,
+                    };
+                    let mut exporters = export::exporters(&args.formats, &config);
+                    run_steps(
+                        &term,
+                        &read_byte_sync,
+                        &recording,
+                        steps,
+                        &capture,
+                        &mut exporters,
+                        &mut screens,
+                    )
+                });
+                if let Err(err) = &result {
+                    error!(plugin = plugin.id, colorscheme, "capture failed: {err:?}");
+                }
+                let entry = CaptureEntry::new(
+                    out_dir.strip_prefix(out_root).unwrap(),
+                    background,
+                    &args.formats,
+                    stylesheet,
+                    normal.is_some(),
+                    screens,
+                    &result,
+                );
+                (entry, normal)
+            };
+            let mut captures = Vec::new();
+            let variants_file = if config.backgrounds.is_empty() {
+                captures.push(run_capture(out_dir, None).0);
+                None
+            } else {
+                let mut variants: Vec<Variant> = Vec::new();
+                for &background in &config.backgrounds {
+                    let (entry, normal) =
+                        run_capture(out_dir.join(background.as_str()), Some(background));
+                    captures.push(entry);
+                    // Failing to load the colorscheme is recorded in the capture entry.
+                    let Some(normal) = normal else {
+                        continue;
+                    };
+                    variants.push(Variant::new(background, normal, variants.first()));
+                }
+                variants::export(&out_dir, &variants);
+                let file = out_dir
+                    .strip_prefix(out_root)
+                    .unwrap()
+                    .join("variants.json");
+                Some(file.to_string_lossy().into_owned())
+            };
+            colorschemes.push(ColorschemeEntry {
+                name: colorscheme.clone(),
+                captured_at,
+                fingerprint,
+                variants: variants_file,
+                captures,
+            });
         }
+        manifest.plugins.push(PluginEntry {
+            id: plugin.id.clone(),
+            name: plugin.name.clone(),
+            url: plugin.url.clone(),
+            colorschemes,
+        });
     }
+    manifest.export(out_root);
     // Wait for neovim to process last commands
     std::thread::sleep(Duration::from_millis(100));
     // kill the process
     nvim_process.kill().unwrap();
+    nvim_process.wait().unwrap();
+    let failed = manifest
+        .plugins
+        .iter()
+        .flat_map(|plugin| &plugin.colorschemes)
+        .flat_map(|colorscheme| &colorscheme.captures)
+        .filter(|capture| capture.failed())
+        .count();
+    if failed > 0 {
+        error!("{failed} captures failed, see manifest.json");
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,410 @@
+//! `manifest.json`, written to the output directory once every colorscheme has been captured, so
+//! that consumers don't have to guess what was captured from the files on disk.
+
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Background, Size},
+    export::Format,
+};
+
+const VERSION: u32 = 1;
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Manifest {
+    version: u32,
+    /// Start of the run, in RFC 3339.
+    captured_at: String,
+    nvim_version: String,
+    /// Size every scenario starts at.
+    size: Size,
+    pub(crate) plugins: Vec<PluginEntry>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub(crate) struct PluginEntry {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) url: String,
+    pub(crate) colorschemes: Vec<ColorschemeEntry>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ColorschemeEntry {
+    pub(crate) name: String,
+    /// Start of the captures, in RFC 3339. Colorschemes skipped as unchanged keep the time of the
+    /// run that captured them.
+    pub(crate) captured_at: String,
+    /// Fingerprint of the inputs of the captures, unset when some of them are unknown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) fingerprint: Option<String>,
+    /// Colors of the colorscheme with every background, set if the config has backgrounds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) variants: Option<String>,
+    /// One capture per background, or a single one with the colorscheme's default background.
+    pub(crate) captures: Vec<CaptureEntry>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CaptureEntry {
+    background: Option<Background>,
+    status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Stylesheet the HTML snapshots need, if they're styled with classes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stylesheet: Option<String>,
+    /// Highlight groups of the colorscheme, unset if the capture failed before loading it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    highlights: Option<String>,
+    /// Contrast report of the snapshots, unset if the capture failed before loading the
+    /// colorscheme.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    contrast: Option<String>,
+    /// Screens in the order the scenarios take them, up to the failing step if the capture failed.
+    screens: Vec<ScreenEntry>,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Ok,
+    Failed,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+struct ScreenEntry {
+    title: String,
+    kind: Kind,
+    /// Path of the screen in every format it was written to, relative to the output directory.
+    files: BTreeMap<String, String>,
+}
+
+/// Screen taken by a scenario step.
+pub(crate) struct Screen {
+    pub(crate) title: String,
+    pub(crate) kind: Kind,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Kind {
+    Snapshot,
+    Animation,
+    Recording,
+}
+
+impl Manifest {
+    pub(crate) fn new(nvim_version: String, size: Size) -> Manifest {
+        Manifest {
+            version: VERSION,
+            captured_at: now(),
+            nvim_version,
+            size,
+            plugins: Vec::new(),
+        }
+    }
+
+    /// Reads the `manifest.json` a previous run wrote to `out_dir`, if any.
+    pub(crate) fn load(out_dir: &Path) -> Option<Manifest> {
+        let path = out_dir.join("manifest.json");
+        let source = std::fs::read_to_string(&path).ok()?;
+        match serde_json::from_str::<Manifest>(&source) {
+            Ok(manifest) if manifest.version == VERSION => Some(manifest),
+            Ok(manifest) => {
+                warn!(
+                    version = manifest.version,
+                    "ignoring manifest of another version"
+                );
+                None
+            }
+            Err(err) => {
+                warn!("ignoring invalid manifest {path:?}: {err}");
+                None
+            }
+        }
+    }
+
+    /// Returns the captures of `colorscheme` of `plugin_id` if they have `fingerprint`, all
+    /// succeeded and all their files are still in `out_dir`.
+    pub(crate) fn unchanged(
+        &self,
+        out_dir: &Path,
+        plugin_id: &str,
+        colorscheme: &str,
+        fingerprint: &str,
+    ) -> Option<&ColorschemeEntry> {
+        let entry = self
+            .plugins
+            .iter()
+            .find(|plugin| plugin.id == plugin_id)?
+            .colorschemes
+            .iter()
+            .find(|entry| entry.name == colorscheme)?;
+        let complete = entry.captures.iter().all(|capture| {
+            !capture.failed()
+                && capture
+                    .screens
+                    .iter()
+                    .flat_map(|screen| screen.files.values())
+                    .chain(&capture.stylesheet)
+                    .chain(&capture.highlights)
+                    .chain(&capture.contrast)
+                    .all(|file| out_dir.join(file).exists())
+        }) && entry
+            .variants
+            .iter()
+            .all(|file| out_dir.join(file).exists());
+        (entry.fingerprint.as_deref() == Some(fingerprint) && complete).then_some(entry)
+    }
+
+    /// Writes `manifest.json` to `out_dir`.
+    pub(crate) fn export(&self, out_dir: &Path) {
+        std::fs::create_dir_all(out_dir).unwrap();
+        let file = std::fs::File::create(out_dir.join("manifest.json")).unwrap();
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self).unwrap();
+    }
+}
+
+impl CaptureEntry {
+    /// Describes a capture written to `dir`, relative to the output directory, that took
+    /// `screens` before ending with `result`.
+    pub(crate) fn new(
+        dir: &Path,
+        background: Option<Background>,
+        formats: &[Format],
+        stylesheet: bool,
+        loaded: bool,
+        screens: Vec<Screen>,
+        result: &eyre::Result<()>,
+    ) -> CaptureEntry {
+        let path = |name: &str, extension: &str| {
+            dir.join(format!("{name}.{extension}"))
+                .to_string_lossy()
+                .into_owned()
+        };
+        let screens = screens
+            .into_iter()
+            .map(|screen| {
+                let files = match screen.kind {
+                    Kind::Snapshot => formats
+                        .iter()
+                        .map(|format| {
+                            let file = path(&screen.title, format.extension());
+                            (format.as_str().to_string(), file)
+                        })
+                        .collect(),
+                    Kind::Animation => formats
+                        .iter()
+                        .filter(|format| format.animates())
+                        .map(|format| {
+                            let file = path(&screen.title, format.extension());
+                            (format.as_str().to_string(), file)
+                        })
+                        .collect(),
+                    Kind::Recording => {
+                        BTreeMap::from([("asciicast".to_string(), path(&screen.title, "cast"))])
+                    }
+                };
+                ScreenEntry {
+                    title: screen.title,
+                    kind: screen.kind,
+                    files,
+                }
+            })
+            .collect();
+        CaptureEntry {
+            background,
+            status: if result.is_ok() {
+                Status::Ok
+            } else {
+                Status::Failed
+            },
+            error: result.as_ref().err().map(|err| format!("{err:#}")),
+            stylesheet: stylesheet.then(|| path("palette", "css")),
+            highlights: loaded.then(|| path("highlights", "json")),
+            contrast: loaded.then(|| path("contrast", "json")),
+            screens,
+        }
+    }
+
+    pub(crate) fn failed(&self) -> bool {
+        matches!(self.status, Status::Failed)
+    }
+}
+
+/// Current time in RFC 3339.
+pub(crate) fn now() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    rfc3339(secs)
+}
+
+/// Formats `secs` since the Unix epoch as an RFC 3339 UTC date.
+fn rfc3339(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let secs_of_day = secs % 86400;
+    // Converts days since the epoch to a civil date, see
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{rfc3339, CaptureEntry, ColorschemeEntry, Kind, Manifest, PluginEntry, Screen};
+    use crate::{config::Size, export::Format};
+
+    /// Empty directory of its own for every test.
+    fn out_dir() -> PathBuf {
+        let dir = crate::temp_path("manifest");
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn capture(result: eyre::Result<()>, stylesheet: bool) -> CaptureEntry {
+        let screens = vec![Screen {
+            title: "Rust".to_string(),
+            kind: Kind::Snapshot,
+        }];
+        CaptureEntry::new(
+            "plugin/colorscheme".as_ref(),
+            None,
+            &[Format::Html],
+            stylesheet,
+            true,
+            screens,
+            &result,
+        )
+    }
+
+    fn manifest(capture: CaptureEntry) -> Manifest {
+        let mut manifest = Manifest::new("v0.9.5".to_string(), Size::default());
+        manifest.plugins.push(PluginEntry {
+            id: "plugin".to_string(),
+            name: "plugin".to_string(),
+            url: "https://example.com/plugin".to_string(),
+            colorschemes: vec![ColorschemeEntry {
+                name: "colorscheme".to_string(),
+                captured_at: rfc3339(0),
+                fingerprint: Some("0123456789abcdef".to_string()),
+                variants: Some("plugin/colorscheme/variants.json".to_string()),
+                captures: vec![capture],
+            }],
+        });
+        manifest
+    }
+
+    /// Writes the files of every capture of `manifest` to `out_dir`.
+    fn write_files(manifest: &Manifest, out_dir: &Path) {
+        let colorschemes = manifest
+            .plugins
+            .iter()
+            .flat_map(|plugin| &plugin.colorschemes);
+        for colorscheme in colorschemes {
+            let files = colorscheme
+                .captures
+                .iter()
+                .flat_map(|capture| {
+                    capture
+                        .screens
+                        .iter()
+                        .flat_map(|screen| screen.files.values())
+                        .chain(&capture.stylesheet)
+                        .chain(&capture.highlights)
+                        .chain(&capture.contrast)
+                })
+                .chain(&colorscheme.variants);
+            for file in files {
+                let path = out_dir.join(file);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, "").unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn unchanged_with_same_fingerprint_and_files() {
+        let out_dir = out_dir();
+        let manifest = manifest(capture(Ok(()), false));
+        write_files(&manifest, &out_dir);
+        let unchanged = manifest.unchanged(&out_dir, "plugin", "colorscheme", "0123456789abcdef");
+        assert!(unchanged.is_some_and(|entry| entry.name == "colorscheme"));
+        assert!(manifest
+            .unchanged(&out_dir, "plugin", "colorscheme", "fedcba9876543210")
+            .is_none());
+        assert!(manifest
+            .unchanged(&out_dir, "plugin", "other", "0123456789abcdef")
+            .is_none());
+        std::fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    #[test]
+    fn changed_when_files_are_missing() {
+        let manifest = manifest(capture(Ok(()), true));
+        for missing in [
+            "Rust.html",
+            "palette.css",
+            "highlights.json",
+            "contrast.json",
+            "variants.json",
+        ] {
+            let out_dir = out_dir();
+            write_files(&manifest, &out_dir);
+            std::fs::remove_file(out_dir.join("plugin/colorscheme").join(missing)).unwrap();
+            assert!(
+                manifest
+                    .unchanged(&out_dir, "plugin", "colorscheme", "0123456789abcdef")
+                    .is_none(),
+                "{missing} is missing"
+            );
+            std::fs::remove_dir_all(out_dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn changed_when_a_capture_failed() {
+        let out_dir = out_dir();
+        let manifest = manifest(capture(Err(eyre::eyre!("timed out")), false));
+        write_files(&manifest, &out_dir);
+        assert!(manifest
+            .unchanged(&out_dir, "plugin", "colorscheme", "0123456789abcdef")
+            .is_none());
+        std::fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    #[test]
+    fn formats_rfc3339() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(rfc3339(1234567890), "2009-02-13T23:31:30Z");
+        assert_eq!(rfc3339(1700000000), "2023-11-14T22:13:20Z");
+        assert_eq!(rfc3339(4107542399), "2100-02-28T23:59:59Z");
+    }
+}
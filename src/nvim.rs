@@ -1,17 +1,25 @@
 use std::{
+    collections::BTreeMap,
     io::Write,
     path::Path,
     process::{Child, Command, Stdio},
+    time::Duration,
 };
 
-use eyre::WrapErr;
+use eyre::{bail, WrapErr};
+use serde::{Deserialize, Serialize};
 
-use crate::{config::Plugin, COLUMNS, LINES, SAMPLES_EXTRACTION_PATH};
+use crate::{
+    config::{Background, Plugin, Size},
+    export::Highlights,
+    SAMPLES_EXTRACTION_PATH,
+};
 
 static CONFIG_DIR: &str = "/tmp/nvim/config";
 static DATA_DIR: &str = "/tmp/nvim/data";
 static STATE_DIR: &str = "/tmp/nvim/state";
-static DEFAULT_PLUGINS: &str = r#"
+/// Plugins installed for every colorscheme, as lazy.nvim specs.
+pub(crate) static DEFAULT_PLUGINS: &str = r#"
 "kyazdani42/nvim-web-devicons",
 {
     "nvim-lualine/lualine.nvim",
@@ -34,7 +42,22 @@ static DEFAULT_PLUGINS: &str = r#"
     end
 },
 "#;
-static OPTIONS: &str = r#"
+/// Runs the Lua chunk in `_A` and encodes its outcome as JSON, so that both its result and its
+/// errors can be read back from `--remote-expr`.
+static EVAL_LUA: &str = r#"
+(function(chunk)
+  local f, err = loadstring(chunk)
+  if not f then
+    return vim.json.encode({ ok = false, error = err })
+  end
+  local ok, result = pcall(f)
+  if not ok then
+    return vim.json.encode({ ok = false, error = tostring(result) })
+  end
+  return vim.json.encode({ ok = true, result = result == nil and vim.NIL or result })
+end)(_A)
+"#;
+pub(crate) static OPTIONS: &str = r#"
 
 vim.o.conceallevel = 2
 vim.o.shortmess = vim.o.shortmess .. "c"
@@ -59,6 +82,8 @@ vim.o.tabstop = 4
 vim.o.formatoptions = "croqnljb"
 vim.o.foldlevelstart=99
 vim.o.mouse = ""
+-- Makes nvim send the colors of `Cursor` and `lCursor` to the terminal so snapshots can show them.
+vim.o.guicursor = "n-v-c-sm:block-Cursor/lCursor,i-ci-ve:ver25-Cursor/lCursor,r-cr-o:hor20-Cursor/lCursor"
 
 vim.bo.expandtab = vim.o.expandtab
 vim.bo.shiftwidth = vim.o.shiftwidth
@@ -76,29 +101,81 @@ vim.wo.relativenumber = true
 vim.wo.colorcolumn = "80"
 "#;
 
-pub(crate) fn command() -> Command {
+pub(crate) fn command(size: Size) -> Command {
     let mut command = Command::new("nvim");
     command
-        .args(&["--listen", "localhost:5009"])
+        .args(["--listen", "localhost:5009"])
         .stdin(Stdio::inherit())
         .stdout(Stdio::piped())
         .current_dir(SAMPLES_EXTRACTION_PATH)
-        .env("LINES", LINES.to_string())
-        .env("COLUMNS", COLUMNS.to_string())
+        .env("LINES", size.lines.to_string())
+        .env("COLUMNS", size.columns.to_string())
+        // nvim only sends cursor shapes and colors to terminals it knows support them, which
+        // the emulated terminal does whatever the terminal this runs in.
+        .env("TERM", "xterm-256color")
         .env("XDG_CONFIG_HOME", CONFIG_DIR)
         .env("XDG_DATA_HOME", DATA_DIR)
         .env("XDG_STATE_HOME", STATE_DIR);
     command
 }
 
-pub(crate) fn spawn() -> Child {
-    command().spawn().expect("failed to spawn nvim")
+pub(crate) fn spawn(size: Size) -> Child {
+    command(size).spawn().expect("failed to spawn nvim")
+}
+
+/// Commits of the installed plugins, by the name lazy.nvim gives them, i.e. the name of their
+/// repository.
+pub(crate) fn plugin_commits() -> eyre::Result<BTreeMap<String, String>> {
+    #[derive(Deserialize)]
+    struct Locked {
+        commit: String,
+    }
+
+    let path = Path::new(CONFIG_DIR).join("nvim").join("lazy-lock.json");
+    let lock =
+        std::fs::read_to_string(&path).wrap_err_with(|| format!("failed to read {path:?}"))?;
+    let lock: BTreeMap<String, Locked> =
+        serde_json::from_str(&lock).wrap_err_with(|| format!("invalid lock file {path:?}"))?;
+    Ok(lock
+        .into_iter()
+        .map(|(name, locked)| (name, locked.commit))
+        .collect())
+}
+
+/// Names lazy.nvim gives the plugins installed for every colorscheme, lazy.nvim included.
+pub(crate) fn default_plugin_names() -> Vec<&'static str> {
+    let mut names = vec!["lazy.nvim"];
+    names.extend(DEFAULT_PLUGINS.lines().filter_map(|line| {
+        let spec = line.trim().strip_suffix(',')?;
+        let (_, name) = spec.strip_prefix('"')?.strip_suffix('"')?.split_once('/')?;
+        Some(name)
+    }));
+    names
+}
+
+/// Version of nvim, e.g. `v0.9.5`.
+pub(crate) fn version() -> eyre::Result<String> {
+    let output = Command::new("nvim")
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output()
+        .wrap_err("failed to run `nvim --version`")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // The first line reads `NVIM v0.9.5`.
+    match stdout
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("NVIM "))
+    {
+        Some(version) => Ok(version.trim().to_string()),
+        None => bail!("unexpected output of `nvim --version`:\n{stdout}"),
+    }
 }
 
 pub(crate) fn send_command(cmd: &str) {
     trace!(cmd, "sent cmd");
     Command::new("nvim")
-        .args(&["--server", "localhost:5009", "--remote-send", cmd])
+        .args(["--server", "localhost:5009", "--remote-send", cmd])
         .stdin(Stdio::null())
         .stdout(Stdio::null())
         .spawn()
@@ -107,6 +184,247 @@ pub(crate) fn send_command(cmd: &str) {
         .unwrap();
 }
 
+#[derive(Deserialize)]
+struct LuaOutcome {
+    ok: bool,
+    #[serde(default)]
+    result: serde_json::Value,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// Runs the Lua chunk `code` in the running nvim and returns the value it returns.
+pub(crate) fn eval_lua(code: &str) -> eyre::Result<serde_json::Value> {
+    trace!(code, "eval lua");
+    // JSON string escapes are also valid in vimscript strings.
+    let code = serde_json::to_string(code)?;
+    let eval_lua = EVAL_LUA
+        .lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let expr = format!("luaeval('{}', {code})", eval_lua.replace('\'', "''"));
+    let output = Command::new("nvim")
+        .args(["--server", "localhost:5009", "--remote-expr", &expr])
+        .stdin(Stdio::null())
+        .output()
+        .wrap_err("failed to run `nvim --remote-expr`")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let outcome: LuaOutcome = serde_json::from_str(stdout.trim()).wrap_err_with(|| {
+        format!(
+            "unexpected output from `nvim --remote-expr`: {stdout}{}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+    })?;
+    if !outcome.ok {
+        bail!(
+            "lua error: {}",
+            outcome.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+    Ok(outcome.result)
+}
+
+/// Lua function formatting the colors of `nvim_get_hl` as `#rrggbb`, keeping unset ones `nil`.
+static HEX: &str = r##"
+local function hex(color)
+  return color and string.format("#%06x", color)
+end
+"##;
+
+/// Colors of the Normal highlight group and the value of `background`.
+#[derive(Clone, Deserialize, Serialize)]
+pub(crate) struct NormalColors {
+    pub(crate) fg: Option<String>,
+    pub(crate) bg: Option<String>,
+    /// Colorschemes may override `background` when they are loaded.
+    pub(crate) background: String,
+}
+
+/// Colors a colorscheme gives the terminal, as set when it's loaded.
+#[derive(Deserialize)]
+pub(crate) struct TerminalColors {
+    /// Values of `g:terminal_color_0` to `g:terminal_color_15`.
+    pub(crate) colors: Vec<Option<String>>,
+    #[serde(flatten)]
+    pub(crate) normal: NormalColors,
+}
+
+/// Loads `colorscheme`, with `background` set beforehand if given, and returns the colors it
+/// gives the terminal and Normal.
+pub(crate) fn load_colorscheme(
+    colorscheme: &str,
+    background: Option<Background>,
+) -> eyre::Result<TerminalColors> {
+    let background = match background {
+        Some(background) => format!("vim.o.background = {:?}", background.as_str()),
+        None => String::new(),
+    };
+    let result = eval_lua(&format!(
+        r##"
+{background}
+-- Variables of the previous colorscheme would be taken for this one's.
+for i = 0, 15 do
+  vim.g["terminal_color_" .. i] = nil
+end
+vim.cmd.colorscheme({colorscheme:?})
+{HEX}
+local hl = vim.api.nvim_get_hl(0, {{ name = "Normal", link = false }})
+local colors = {{}}
+for i = 0, 15 do
+  colors[i + 1] = vim.g["terminal_color_" .. i] or vim.NIL
+end
+return {{
+  colors = colors,
+  fg = hex(hl.fg),
+  bg = hex(hl.bg),
+  background = vim.o.background,
+}}
+"##
+    ))?;
+    Ok(serde_json::from_value(result)?)
+}
+
+/// Highlight group with its links followed, as defined by the loaded colorscheme.
+#[derive(Deserialize, Serialize)]
+pub(crate) struct Highlight {
+    /// Group the definition comes from, if it's a link.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) link: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) fg: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) bg: Option<String>,
+    /// Color of underlines.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) sp: Option<String>,
+    /// Attributes that are set, e.g. `bold` or `undercurl`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) attributes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) blend: Option<u8>,
+}
+
+/// Returns every highlight group defined in nvim, by name.
+static HIGHLIGHTS: &str = r##"
+local attributes = {
+  "bold", "italic", "underline", "undercurl", "underdouble", "underdotted", "underdashed",
+  "strikethrough", "reverse", "standout", "nocombine", "altfont",
+}
+local groups = {}
+for name, definition in pairs(vim.api.nvim_get_hl(0, {})) do
+  local hl = vim.api.nvim_get_hl(0, { name = name, link = false })
+  local set = {}
+  for _, attribute in ipairs(attributes) do
+    if hl[attribute] then
+      table.insert(set, attribute)
+    end
+  end
+  groups[name] = {
+    link = definition.link,
+    fg = hex(hl.fg),
+    bg = hex(hl.bg),
+    sp = hex(hl.sp),
+    -- An empty table would be encoded as an object.
+    attributes = #set > 0 and set or nil,
+    blend = hl.blend,
+  }
+end
+return groups
+"##;
+
+pub(crate) fn highlights() -> eyre::Result<BTreeMap<String, Highlight>> {
+    let result = eval_lua(&format!("{HEX}{HIGHLIGHTS}"))?;
+    Ok(serde_json::from_value(result)?)
+}
+
+/// Returns the highlight group of every cell of the screen that shows buffer text, as
+/// `[line, column, group]` with zero-based positions.
+static SCREEN_HIGHLIGHTS: &str = r#"
+local highlights = {}
+for _, win in ipairs(vim.api.nvim_tabpage_list_wins(0)) do
+  local buf = vim.api.nvim_win_get_buf(win)
+  for lnum = vim.fn.line("w0", win), vim.fn.line("w$", win) do
+    local line = vim.api.nvim_buf_get_lines(buf, lnum - 1, lnum, false)[1] or ""
+    for col = 0, #line - 1 do
+      local pos = vim.fn.screenpos(win, lnum, col + 1)
+      if pos.row > 0 then
+        local items = vim.inspect_pos(buf, lnum - 1, col)
+        local group
+        -- Later kinds of highlights are drawn over the earlier ones.
+        for _, kind in ipairs({ "syntax", "treesitter", "semantic_tokens", "extmarks" }) do
+          for _, item in ipairs(items[kind] or {}) do
+            group = item.hl_group or (item.opts and item.opts.hl_group) or group
+          end
+        end
+        if group then
+          table.insert(highlights, { pos.row - 1, pos.col - 1, group })
+        end
+      end
+    end
+  end
+end
+-- An empty table would be encoded as an object.
+return #highlights > 0 and highlights or vim.NIL
+"#;
+
+pub(crate) fn screen_highlights() -> eyre::Result<Highlights> {
+    let result = eval_lua(SCREEN_HIGHLIGHTS)?;
+    let highlights: Option<Vec<(usize, usize, String)>> = serde_json::from_value(result)?;
+    Ok(highlights
+        .into_iter()
+        .flatten()
+        .map(|(line, column, group)| ((line, column), group))
+        .collect())
+}
+
+/// Resizes nvim's screen. Without a tty to read the new size from, nvim only learns about it
+/// through the `columns` and `lines` options.
+pub(crate) fn resize(size: Size) -> eyre::Result<()> {
+    eval_lua(&format!(
+        "vim.o.columns = {}; vim.o.lines = {}",
+        size.columns, size.lines
+    ))
+    .map(drop)
+}
+
+/// Sends `keys` one at a time, sleeping `delay` after each of them.
+pub(crate) fn type_keys(keys: &str, delay: Duration) {
+    for key in split_keys(keys) {
+        send_command(&key);
+        std::thread::sleep(delay);
+    }
+}
+
+/// Splits `keys` into single keys, keeping key codes like `<CR>` or `<C-w>` together.
+///
+/// A `<` that doesn't start a key code is sent as `<lt>`.
+fn split_keys(keys: &str) -> Vec<String> {
+    let mut split = Vec::new();
+    let mut rest = keys;
+    while let Some(ch) = rest.chars().next() {
+        if ch != '<' {
+            split.push(ch.to_string());
+            rest = &rest[ch.len_utf8()..];
+            continue;
+        }
+        let key_code_end = rest.find('>').filter(|&end| {
+            end > 1 && !rest[1..end].contains(|ch: char| ch == '<' || ch.is_whitespace())
+        });
+        match key_code_end {
+            Some(end) => {
+                split.push(rest[..=end].to_string());
+                rest = &rest[end + 1..];
+            }
+            None => {
+                split.push("<lt>".to_string());
+                rest = &rest[1..];
+            }
+        }
+    }
+    split
+}
+
 pub(crate) fn setup(plugins: &[Plugin]) {
     let plugins_object = build_plugins_object(plugins);
     let lazy_bootstrap = format!(
@@ -130,7 +448,7 @@ require("lazy").setup({plugins_object})
     // setup init.lua
     std::fs::create_dir_all(CONFIG_DIR).unwrap();
     let init_lua = Path::new(CONFIG_DIR).join("nvim").join("init.lua");
-    std::fs::create_dir(init_lua.parent().unwrap())
+    std::fs::create_dir_all(init_lua.parent().unwrap())
         .wrap_err("failed to create `nvim` directory")
         .unwrap();
     let mut init_lua = std::fs::OpenOptions::new()
@@ -144,7 +462,7 @@ require("lazy").setup({plugins_object})
 
     // install plugins
     Command::new("nvim")
-        .args(&["--headless", "+Lazy! sync", "+qa"])
+        .args(["--headless", "+Lazy! sync", "+qa"])
         .env("XDG_CONFIG_HOME", CONFIG_DIR)
         .env("XDG_DATA_HOME", DATA_DIR)
         .env("XDG_STATE_HOME", STATE_DIR)
@@ -161,5 +479,50 @@ fn build_plugins_object(plugins: &[Plugin]) -> String {
         plugins_buf.push_str(&format!("\"{}\"", plugin.url));
         plugins_buf.push_str(",\n");
     }
-    return format!("{{\n{plugins_buf}\n}}");
+    format!("{{\n{plugins_buf}\n}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_plugin_names, split_keys};
+
+    #[test]
+    fn default_plugins_are_named_after_their_repository() {
+        assert_eq!(
+            default_plugin_names(),
+            [
+                "lazy.nvim",
+                "nvim-web-devicons",
+                "lualine.nvim",
+                "plenary.nvim",
+                "telescope.nvim",
+                "nvim-treesitter",
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_key_codes() {
+        assert_eq!(split_keys("a<C-x>b"), ["a", "<C-x>", "b"]);
+        assert_eq!(split_keys("<CR><Esc>"), ["<CR>", "<Esc>"]);
+        assert_eq!(split_keys("<lt>"), ["<lt>"]);
+    }
+
+    #[test]
+    fn escapes_lone_less_than() {
+        assert_eq!(split_keys("a < b"), ["a", " ", "<lt>", " ", "b"]);
+        assert_eq!(split_keys("<"), ["<lt>"]);
+        assert_eq!(split_keys("<<CR>"), ["<lt>", "<CR>"]);
+        assert_eq!(split_keys("<>"), ["<lt>", ">"]);
+    }
+
+    #[test]
+    fn splits_non_ascii_text() {
+        assert_eq!(
+            split_keys("ié -> x<Esc>"),
+            ["i", "é", " ", "-", ">", " ", "x", "<Esc>"]
+        );
+        assert_eq!(split_keys("<é>"), ["<é>"]);
+        assert_eq!(split_keys("日本<CR>"), ["日", "本", "<CR>"]);
+    }
 }
@@ -0,0 +1,779 @@
+use std::{collections::HashSet, fmt, path::Path};
+
+use alacritty_terminal::term::color::Rgb;
+use eyre::{bail, WrapErr};
+use regex::Regex;
+use serde::{
+    de::{DeserializeSeed, Error, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+use crate::config::{Plugin, Size};
+
+static DEFAULT_SCENARIOS: &str = include_str!("../scenarios/default.toml");
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) enum Action {
+    NvimCmd(String),
+    Snapshot {
+        #[serde(deserialize_with = "title")]
+        title: String,
+    },
+    WaitFor(WaitFor),
+    TypeKeys(TypeKeys),
+    Lua(Lua),
+    /// Starts recording nvim's output, written to `{title}.cast` once the recording stops.
+    StartRecording {
+        #[serde(deserialize_with = "title")]
+        title: String,
+    },
+    StopRecording,
+    /// Starts an animation, written to `{title}.svg` or `{title}.gif` once it stops.
+    StartAnimation {
+        #[serde(deserialize_with = "title")]
+        title: String,
+    },
+    /// Adds the screen to the current animation, shown for `duration_ms` before the next frame.
+    Frame {
+        #[serde(default = "default_frame_ms")]
+        duration_ms: u64,
+    },
+    StopAnimation,
+    /// Resizes the terminal and nvim until the end of the scenario.
+    Resize(Size),
+}
+
+/// Runs a Lua chunk in nvim, failing the scenario if it errors or returns something unexpected.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Lua {
+    pub(crate) code: String,
+    /// Value the chunk must return, compared after encoding it as JSON.
+    #[serde(default)]
+    pub(crate) expect: Option<serde_json::Value>,
+}
+
+/// Types keys one at a time, e.g. to capture a completion popup while in insert mode.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct TypeKeys {
+    /// Keys in the same notation as `nvim_cmd`, `<CR>` or `<C-w>` count as a single key.
+    pub(crate) keys: String,
+    /// Time to wait after each key.
+    #[serde(default = "default_key_delay_ms")]
+    pub(crate) delay_ms: u64,
+}
+
+/// Waits until a pattern is on the screen, e.g. to take a snapshot only once a picker is drawn.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct WaitFor {
+    #[serde(deserialize_with = "regex")]
+    pub(crate) pattern: Regex,
+    /// Lines searched for the pattern as `[start, end)`. The whole screen by default.
+    #[serde(default, deserialize_with = "region")]
+    pub(crate) lines: Option<[usize; 2]>,
+    /// Columns searched for the pattern as `[start, end)`. The whole screen by default.
+    #[serde(default, deserialize_with = "region")]
+    pub(crate) columns: Option<[usize; 2]>,
+    /// Foreground color, as `#rrggbb`, that every char of the match must have.
+    #[serde(default, deserialize_with = "hex_color")]
+    pub(crate) fg: Option<String>,
+    /// Background color, as `#rrggbb`, that every char of the match must have.
+    #[serde(default, deserialize_with = "hex_color")]
+    pub(crate) bg: Option<String>,
+    #[serde(default = "default_timeout_ms")]
+    pub(crate) timeout_ms: u64,
+}
+
+impl Action {
+    /// Replaces `{colorscheme}` in the action's arguments.
+    pub(crate) fn interpolate(&self, colorscheme: &str) -> Action {
+        match self {
+            Action::NvimCmd(cmd) => Action::NvimCmd(cmd.replace("{colorscheme}", colorscheme)),
+            Action::TypeKeys(type_keys) => Action::TypeKeys(TypeKeys {
+                keys: type_keys.keys.replace("{colorscheme}", colorscheme),
+                delay_ms: type_keys.delay_ms,
+            }),
+            Action::Lua(lua) => Action::Lua(Lua {
+                code: lua.code.replace("{colorscheme}", colorscheme),
+                expect: lua.expect.clone(),
+            }),
+            action => action.clone(),
+        }
+    }
+}
+
+/// Deserialized by [`ScenarioSeed`], which checks it against the scenarios before it.
+#[derive(Debug)]
+pub(crate) struct Scenario {
+    pub(crate) name: String,
+    pub(crate) steps: Vec<Action>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioFile {
+    #[serde(deserialize_with = "scenarios")]
+    scenarios: Vec<Scenario>,
+}
+
+/// Loads the scenarios in `path`, which is parsed as JSON if its extension is `json` and as TOML
+/// otherwise.
+pub(crate) fn load(path: &Path) -> eyre::Result<Vec<Scenario>> {
+    let source = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("failed to read scenario file {path:?}"))?;
+    let file: ScenarioFile = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&source).map_err(eyre::Report::new)
+    } else {
+        toml::from_str(&source).map_err(eyre::Report::new)
+    }
+    .wrap_err_with(|| format!("invalid scenario file {path:?}"))?;
+    Ok(file.scenarios)
+}
+
+/// Scenarios bundled in the binary, used when no scenario file is given.
+pub(crate) fn builtin() -> Vec<Scenario> {
+    let file: ScenarioFile =
+        toml::from_str(DEFAULT_SCENARIOS).expect("bundled scenarios must be valid");
+    file.scenarios
+}
+
+/// Keeps only the scenarios named in `names`, in the order they appear in the file.
+pub(crate) fn select(scenarios: Vec<Scenario>, names: &[String]) -> eyre::Result<Vec<Scenario>> {
+    if names.is_empty() {
+        return Ok(scenarios);
+    }
+    for name in names {
+        if !scenarios.iter().any(|scenario| &scenario.name == name) {
+            let available = scenarios
+                .iter()
+                .map(|scenario| scenario.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            bail!("unknown scenario `{name}`, available scenarios: {available}");
+        }
+    }
+    Ok(scenarios
+        .into_iter()
+        .filter(|scenario| names.contains(&scenario.name))
+        .collect())
+}
+
+/// Returns the steps run for every colorscheme of `plugin`: the steps of `scenarios` with the
+/// snapshots in `plugin.replace_steps` replaced, followed by `plugin.extra_steps`. Each of them
+/// starts with the terminal resized back to `size`.
+pub(crate) fn plugin_steps(
+    scenarios: &[Scenario],
+    plugin: &Plugin,
+    size: Size,
+) -> eyre::Result<Vec<Action>> {
+    let mut steps = Vec::new();
+    let mut replaced = HashSet::new();
+    for scenario in scenarios {
+        steps.push(Action::Resize(size));
+        for action in &scenario.steps {
+            let replacement = match action {
+                Action::Snapshot { title } => plugin.replace_steps.get_key_value(title),
+                _ => None,
+            };
+            match replacement {
+                Some((title, replacement)) => {
+                    steps.extend(replacement.iter().cloned());
+                    replaced.insert(title);
+                }
+                None => steps.push(action.clone()),
+            }
+        }
+    }
+    if !plugin.extra_steps.is_empty() {
+        steps.push(Action::Resize(size));
+        steps.extend(plugin.extra_steps.iter().cloned());
+    }
+
+    for title in plugin.replace_steps.keys() {
+        if !replaced.contains(title) {
+            bail!(
+                "plugin `{}` replaces the steps of snapshot `{title}` but no scenario takes it",
+                plugin.id
+            );
+        }
+    }
+    let mut titles = HashSet::new();
+    let mut recordings = HashSet::new();
+    for action in &steps {
+        match action {
+            Action::Snapshot { title } | Action::StartAnimation { title }
+                if !titles.insert(title) =>
+            {
+                bail!(
+                    "plugin `{}` takes more than one snapshot or animation titled `{title}`",
+                    plugin.id
+                )
+            }
+            Action::StartRecording { title } if !recordings.insert(title) => bail!(
+                "plugin `{}` makes more than one recording titled `{title}`",
+                plugin.id
+            ),
+            _ => {}
+        }
+    }
+    check_blocks(&steps).map_err(|err| eyre::eyre!("plugin `{}` {err}", plugin.id))?;
+    Ok(steps)
+}
+
+/// Checks that recordings and animations are started and stopped in pairs, that frames are only
+/// taken during an animation, and that neither is resized.
+fn check_blocks(steps: &[Action]) -> Result<(), String> {
+    let mut recording = None;
+    // Title of the current animation together with its number of frames.
+    let mut animation = None;
+    for action in steps {
+        match action {
+            Action::StartRecording { title } => {
+                if let Some(current) = recording {
+                    return Err(format!(
+                        "starts recording `{title}` while still recording `{current}`"
+                    ));
+                }
+                recording = Some(title);
+            }
+            Action::StopRecording if recording.take().is_none() => {
+                return Err("stops recording without having started one".to_string());
+            }
+            Action::StartAnimation { title } => {
+                if let Some((current, _)) = animation {
+                    return Err(format!(
+                        "starts animation `{title}` while still in animation `{current}`"
+                    ));
+                }
+                animation = Some((title, 0));
+            }
+            Action::Frame { .. } => match &mut animation {
+                Some((_, frames)) => *frames += 1,
+                None => return Err("takes a frame outside of an animation".to_string()),
+            },
+            Action::StopAnimation => match animation.take() {
+                Some((title, 0)) => return Err(format!("animation `{title}` has no frames")),
+                Some(_) => {}
+                None => return Err("stops an animation without having started one".to_string()),
+            },
+            Action::Resize(_) => {
+                if let Some(title) = recording {
+                    return Err(format!("resizes while recording `{title}`"));
+                }
+                if let Some((title, _)) = animation {
+                    return Err(format!("resizes during animation `{title}`"));
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(title) = recording {
+        return Err(format!("never stops recording `{title}`"));
+    }
+    if let Some((title, _)) = animation {
+        return Err(format!("never stops animation `{title}`"));
+    }
+    Ok(())
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_key_delay_ms() -> u64 {
+    50
+}
+
+fn default_frame_ms() -> u64 {
+    500
+}
+
+fn regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(D::Error::custom)
+}
+
+/// Lines or columns searched by `wait_for`, which can't start after they end.
+fn region<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<[usize; 2]>, D::Error> {
+    let [start, end] = <[usize; 2]>::deserialize(deserializer)?;
+    if start > end {
+        return Err(D::Error::custom(format!(
+            "wait_for region [{start}, {end}) starts after it ends"
+        )));
+    }
+    Ok(Some([start, end]))
+}
+
+fn hex_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    let color = String::deserialize(deserializer)?;
+    let rgb: Rgb = color
+        .parse()
+        .map_err(|()| D::Error::custom(format!("`{color}` is not a `#rrggbb` color")))?;
+    Ok(Some(rgb.to_string()))
+}
+
+/// Names of the reports written next to the screens of a capture, as `{name}.json`.
+const REPORTS: [&str; 3] = ["contrast", "highlights", "variants"];
+
+/// Titles end up in file names, so they can't be empty, contain path separators or be the name of
+/// a report.
+fn title<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let title = String::deserialize(deserializer)?;
+    if title.trim().is_empty() {
+        return Err(D::Error::custom("title can't be empty"));
+    }
+    if title.contains(['/', '\\']) || title == "." || title == ".." {
+        return Err(D::Error::custom(format!(
+            "title `{title}` can't be used as a file name"
+        )));
+    }
+    // File names may be case-insensitive.
+    if let Some(report) = REPORTS
+        .iter()
+        .find(|report| report.eq_ignore_ascii_case(&title))
+    {
+        return Err(D::Error::custom(format!(
+            "title `{title}` is reserved for {report}.json"
+        )));
+    }
+    Ok(title)
+}
+
+/// Names and titles of the scenarios read so far. Snapshots and animations of every scenario are
+/// written to the same directory, so both must be unique across the file.
+#[derive(Default)]
+struct Seen {
+    names: HashSet<String>,
+    titles: HashSet<String>,
+}
+
+/// Reads the scenarios one at a time, so that an invalid one is reported at its own position in
+/// the file rather than at the start of the list.
+fn scenarios<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Scenario>, D::Error> {
+    struct ScenariosVisitor;
+
+    impl<'de> Visitor<'de> for ScenariosVisitor {
+        type Value = Vec<Scenario>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a list of scenarios")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut seen = Seen::default();
+            let mut scenarios = Vec::new();
+            while let Some(scenario) = seq.next_element_seed(ScenarioSeed(&mut seen))? {
+                scenarios.push(scenario);
+            }
+            Ok(scenarios)
+        }
+    }
+
+    deserializer.deserialize_seq(ScenariosVisitor)
+}
+
+struct ScenarioSeed<'a>(&'a mut Seen);
+
+impl<'de> DeserializeSeed<'de> for ScenarioSeed<'_> {
+    type Value = Scenario;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Scenario, D::Error> {
+        deserializer.deserialize_struct("Scenario", SCENARIO_FIELDS, self)
+    }
+}
+
+const SCENARIO_FIELDS: &[&str] = &["name", "steps"];
+
+impl<'de> Visitor<'de> for ScenarioSeed<'_> {
+    type Value = Scenario;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a scenario")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Scenario, A::Error> {
+        let mut name = None;
+        let mut steps = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "name" if name.is_some() => return Err(A::Error::duplicate_field("name")),
+                "name" => name = Some(map.next_value_seed(NameSeed(&mut self.0.names))?),
+                "steps" if steps.is_some() => return Err(A::Error::duplicate_field("steps")),
+                "steps" => steps = Some(map.next_value_seed(StepsSeed(&mut self.0.titles))?),
+                _ => return Err(A::Error::unknown_field(&key, SCENARIO_FIELDS)),
+            }
+        }
+        Ok(Scenario {
+            name: name.ok_or_else(|| A::Error::missing_field("name"))?,
+            steps: steps.ok_or_else(|| A::Error::missing_field("steps"))?,
+        })
+    }
+}
+
+struct NameSeed<'a>(&'a mut HashSet<String>);
+
+impl<'de> DeserializeSeed<'de> for NameSeed<'_> {
+    type Value = String;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<String, D::Error> {
+        let name = title(deserializer)?;
+        if !self.0.insert(name.clone()) {
+            return Err(D::Error::custom(format!("duplicate scenario `{name}`")));
+        }
+        Ok(name)
+    }
+}
+
+struct StepsSeed<'a>(&'a mut HashSet<String>);
+
+impl<'de> DeserializeSeed<'de> for StepsSeed<'_> {
+    type Value = Vec<Action>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Vec<Action>, D::Error> {
+        let steps = Vec::<Action>::deserialize(deserializer)?;
+        for action in &steps {
+            if let Action::Snapshot { title } | Action::StartAnimation { title } = action {
+                if !self.0.insert(title.clone()) {
+                    return Err(D::Error::custom(format!(
+                        "duplicate snapshot or animation title `{title}`"
+                    )));
+                }
+            }
+        }
+        check_blocks(&steps).map_err(|err| D::Error::custom(format!("scenario {err}")))?;
+        Ok(steps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plugin_steps, Action, Scenario, ScenarioFile};
+    use crate::config::{Plugin, Size};
+
+    fn parse(source: &str) -> Result<Vec<Scenario>, String> {
+        toml::from_str::<ScenarioFile>(source)
+            .map(|file| file.scenarios)
+            .map_err(|err| err.to_string())
+    }
+
+    /// Asserts that `source` is rejected with `message`, reported at `line`.
+    fn assert_invalid(source: &str, line: usize, message: &str) {
+        let err = parse(source).unwrap_err();
+        assert!(err.contains(message), "{err:?} doesn't mention {message:?}");
+        assert!(
+            err.starts_with(&format!("TOML parse error at line {line},")),
+            "{err:?} isn't at line {line}"
+        );
+    }
+
+    #[test]
+    fn parses_scenarios() {
+        let scenarios = parse(
+            r#"
+            [[scenarios]]
+            name = "editor"
+            steps = [
+                { nvim_cmd = ":e sample.rs<CR>" },
+                { wait_for = { pattern = "fn main", lines = [0, 10] } },
+                { snapshot = { title = "Rust" } },
+            ]
+
+            [[scenarios]]
+            name = "picker"
+            steps = [
+                { start_animation = { title = "Picker" } },
+                { type_keys = { keys = "<C-p>main" } },
+                { frame = {} },
+                "stop_animation",
+            ]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(scenarios.len(), 2);
+        assert_eq!(scenarios[0].name, "editor");
+        assert!(
+            matches!(&scenarios[0].steps[0], Action::NvimCmd(cmd) if cmd == ":e sample.rs<CR>")
+        );
+        assert!(
+            matches!(&scenarios[0].steps[1], Action::WaitFor(wait) if wait.lines == Some([0, 10]))
+        );
+        assert!(matches!(
+            &scenarios[1].steps[2],
+            Action::Frame { duration_ms: 500 }
+        ));
+    }
+
+    #[test]
+    fn parses_json_scenarios() {
+        let file: ScenarioFile = serde_json::from_str(
+            r#"{ "scenarios": [{ "name": "editor", "steps": [{ "snapshot": { "title": "Rust" } }] }] }"#,
+        )
+        .unwrap();
+        assert_eq!(file.scenarios[0].name, "editor");
+    }
+
+    #[test]
+    fn builtin_scenarios_are_valid() {
+        assert!(!super::builtin().is_empty());
+    }
+
+    #[test]
+    fn rejects_titles_that_are_not_file_names() {
+        let source = |title: &str| {
+            format!(
+                "[[scenarios]]\nname = \"a\"\nsteps = [\n  {{ snapshot = {{ title = \"{title}\" }} }},\n]\n"
+            )
+        };
+        assert_invalid(&source(" "), 4, "title can't be empty");
+        assert_invalid(
+            &source("a/b"),
+            4,
+            "title `a/b` can't be used as a file name",
+        );
+        assert_invalid(&source(".."), 4, "title `..` can't be used as a file name");
+        assert_invalid(
+            &source("contrast"),
+            4,
+            "title `contrast` is reserved for contrast.json",
+        );
+        assert_invalid(
+            &source("Highlights"),
+            4,
+            "title `Highlights` is reserved for highlights.json",
+        );
+        assert_invalid(
+            &source("variants"),
+            4,
+            "title `variants` is reserved for variants.json",
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_scenarios() {
+        assert_invalid(
+            r#"
+[[scenarios]]
+name = "a"
+steps = []
+
+[[scenarios]]
+name = "a"
+steps = []
+"#,
+            7,
+            "duplicate scenario `a`",
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_titles_across_scenarios() {
+        assert_invalid(
+            r#"
+[[scenarios]]
+name = "a"
+steps = [{ snapshot = { title = "Rust" } }]
+
+[[scenarios]]
+name = "b"
+steps = [{ start_animation = { title = "Rust" } }, { frame = {} }, "stop_animation"]
+"#,
+            8,
+            "duplicate snapshot or animation title `Rust`",
+        );
+    }
+
+    #[test]
+    fn rejects_unbalanced_blocks() {
+        let source = |steps: &str| {
+            format!("[[scenarios]]\nname = \"a\"\nsteps = []\n\n[[scenarios]]\nname = \"b\"\nsteps = [{steps}]\n")
+        };
+        let cases = [
+            (
+                r#"{ start_recording = { title = "r" } }"#,
+                "never stops recording `r`",
+            ),
+            (
+                r#"{ start_recording = { title = "r" } }, { start_recording = { title = "s" } }"#,
+                "starts recording `s` while still recording `r`",
+            ),
+            (
+                r#""stop_recording""#,
+                "stops recording without having started one",
+            ),
+            (
+                r#"{ start_animation = { title = "a" } }"#,
+                "never stops animation `a`",
+            ),
+            (
+                r#"{ start_animation = { title = "a" } }, "stop_animation""#,
+                "animation `a` has no frames",
+            ),
+            (r#"{ frame = {} }"#, "takes a frame outside of an animation"),
+            (
+                r#""stop_animation""#,
+                "stops an animation without having started one",
+            ),
+            (
+                r#"{ start_animation = { title = "a" } }, { resize = { columns = 80, lines = 24 } }"#,
+                "resizes during animation `a`",
+            ),
+        ];
+        for (steps, message) in cases {
+            assert_invalid(&source(steps), 7, message);
+        }
+    }
+
+    #[test]
+    fn rejects_reversed_regions() {
+        let source = |region: &str| {
+            format!(
+                "[[scenarios]]\nname = \"a\"\nsteps = [\n  {{ nvim_cmd = \":e sample.rs<CR>\" }},\n  {{ wait_for = {{ pattern = \"fn\", {region} }} }},\n]\n"
+            )
+        };
+        assert_invalid(
+            &source("lines = [10, 5]"),
+            5,
+            "wait_for region [10, 5) starts after it ends",
+        );
+        assert_invalid(
+            &source("columns = [3, 2]"),
+            5,
+            "wait_for region [3, 2) starts after it ends",
+        );
+        assert!(parse(&source("lines = [5, 5], columns = [0, 80]")).is_ok());
+    }
+
+    fn plugin(overrides: serde_json::Value) -> Plugin {
+        let mut plugin = serde_json::json!({
+            "id": "io.github.someone.theme",
+            "url": "https://github.com/someone/theme.nvim",
+            "name": "theme",
+        });
+        plugin
+            .as_object_mut()
+            .unwrap()
+            .extend(overrides.as_object().unwrap().clone());
+        serde_json::from_value(plugin).unwrap()
+    }
+
+    /// Steps of the plugin, written as the command they send or the screen they take.
+    fn describe(steps: &[Action]) -> Vec<String> {
+        steps
+            .iter()
+            .map(|action| match action {
+                Action::NvimCmd(cmd) => cmd.clone(),
+                Action::Snapshot { title } => format!("snapshot {title}"),
+                Action::Resize(size) => format!("resize {}x{}", size.columns, size.lines),
+                action => format!("{action:?}"),
+            })
+            .collect()
+    }
+
+    const SCENARIOS: &str = r#"
+[[scenarios]]
+name = "editor"
+steps = [
+    { nvim_cmd = ":tabnew<CR>" },
+    { nvim_cmd = ":e sample.rs<CR>" },
+    { snapshot = { title = "Rust" } },
+    { nvim_cmd = ":e sample.tsx<CR>" },
+    { snapshot = { title = "Typescript" } },
+]
+"#;
+
+    fn steps(overrides: serde_json::Value) -> eyre::Result<Vec<Action>> {
+        let scenarios = parse(SCENARIOS).unwrap();
+        plugin_steps(&scenarios, &plugin(overrides), Size::default())
+    }
+
+    #[test]
+    fn plugin_without_overrides_runs_the_scenarios() {
+        let steps = steps(serde_json::json!({})).unwrap();
+        assert_eq!(
+            describe(&steps),
+            [
+                "resize 110x40",
+                ":tabnew<CR>",
+                ":e sample.rs<CR>",
+                "snapshot Rust",
+                ":e sample.tsx<CR>",
+                "snapshot Typescript",
+            ]
+        );
+    }
+
+    #[test]
+    fn extra_steps_run_after_the_scenarios() {
+        let steps = steps(serde_json::json!({
+            "extraSteps": [{ "nvim_cmd": ":Lualine<CR>" }, { "snapshot": { "title": "Lualine" } }],
+        }))
+        .unwrap();
+        assert_eq!(
+            describe(&steps)[5..],
+            [
+                "snapshot Typescript",
+                "resize 110x40",
+                ":Lualine<CR>",
+                "snapshot Lualine",
+            ]
+        );
+    }
+
+    #[test]
+    fn replace_steps_replace_only_the_snapshot() {
+        let steps = steps(serde_json::json!({
+            "replaceSteps": {
+                "Rust": [{ "nvim_cmd": ":set cursorline<CR>" }, { "snapshot": { "title": "Rust" } }],
+            },
+        }))
+        .unwrap();
+        assert_eq!(
+            describe(&steps),
+            [
+                "resize 110x40",
+                ":tabnew<CR>",
+                ":e sample.rs<CR>",
+                ":set cursorline<CR>",
+                "snapshot Rust",
+                ":e sample.tsx<CR>",
+                "snapshot Typescript",
+            ]
+        );
+    }
+
+    #[test]
+    fn replace_steps_need_a_known_snapshot() {
+        let err = steps(serde_json::json!({ "replaceSteps": { "Lua": [] } })).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "plugin `io.github.someone.theme` replaces the steps of snapshot `Lua` but no scenario \
+             takes it"
+        );
+    }
+
+    #[test]
+    fn plugin_steps_are_interpolated() {
+        let steps = steps(serde_json::json!({
+            "extraSteps": [
+                { "nvim_cmd": ":colorscheme {colorscheme}<CR>" },
+                { "type_keys": { "keys": ":echo '{colorscheme}'<CR>" } },
+                { "lua": { "code": "return vim.g.colors_name == '{colorscheme}'", "expect": true } },
+            ],
+        }))
+        .unwrap();
+        let steps = steps[steps.len() - 3..]
+            .iter()
+            .map(|action| action.interpolate("tokyonight"))
+            .collect::<Vec<_>>();
+        assert!(matches!(&steps[0], Action::NvimCmd(cmd) if cmd == ":colorscheme tokyonight<CR>"));
+        assert!(
+            matches!(&steps[1], Action::TypeKeys(keys) if keys.keys == ":echo 'tokyonight'<CR>")
+        );
+        assert!(
+            matches!(&steps[2], Action::Lua(lua) if lua.code == "return vim.g.colors_name == 'tokyonight'")
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    ops::Range,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use alacritty_terminal::{
+    event::VoidListener,
+    grid::Dimensions,
+    index::{Column, Line},
+    term::cell::Cell,
+    Grid, Term,
+};
+use eyre::bail;
+
+use crate::{color::Palette, export, scenario::WaitFor};
+
+/// How often the grid is checked while waiting for a pattern.
+const POLL_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Text of a line of the grid together with the column of every char.
+struct LineText {
+    text: String,
+    columns: Vec<(usize, usize)>,
+}
+
+impl LineText {
+    fn new(grid: &Grid<Cell>, line: usize, columns: Range<usize>) -> LineText {
+        let row = &grid[Line(line as i32)];
+        let mut text = String::new();
+        let mut byte_columns = Vec::new();
+        for col in columns {
+            let Some(cell_text) = export::cell_text(&row[Column(col)]) else {
+                continue;
+            };
+            for (offset, _) in cell_text.char_indices() {
+                byte_columns.push((text.len() + offset, col));
+            }
+            text.push_str(&cell_text);
+        }
+        LineText {
+            text,
+            columns: byte_columns,
+        }
+    }
+
+    /// Columns of the chars in the byte range `bytes`.
+    fn columns(&self, bytes: Range<usize>) -> impl Iterator<Item = usize> + '_ {
+        self.columns
+            .iter()
+            .filter(move |(byte, _)| bytes.contains(byte))
+            .map(|(_, col)| *col)
+    }
+}
+
+/// Blocks until the pattern of `wait` is on the screen, or fails once its timeout is reached.
+pub(crate) fn wait_for(
+    term: &Mutex<Term<VoidListener>>,
+    wait: &WaitFor,
+    palette: &Palette,
+) -> eyre::Result<()> {
+    let start = Instant::now();
+    loop {
+        let grid = {
+            let term = term.lock().unwrap();
+            term.grid().clone()
+        };
+        if matches(&grid, wait, palette) {
+            trace!(pattern = %wait.pattern, elapsed = ?start.elapsed(), "found pattern");
+            return Ok(());
+        }
+        if start.elapsed() >= Duration::from_millis(wait.timeout_ms) {
+            let screen = region_text(&grid, wait).join("\n");
+            bail!(
+                "timed out after {}ms waiting for `{}` to appear, the screen was:\n{screen}",
+                wait.timeout_ms,
+                wait.pattern
+            );
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn matches(grid: &Grid<Cell>, wait: &WaitFor, palette: &Palette) -> bool {
+    let (lines, columns) = region(grid, wait);
+    lines.into_iter().any(|line| {
+        let line_text = LineText::new(grid, line, columns.clone());
+        let row = &grid[Line(line as i32)];
+        wait.pattern.find_iter(&line_text.text).any(|found| {
+            line_text.columns(found.range()).all(|col| {
+                let cell = &row[Column(col)];
+                let fg_matches = wait
+                    .fg
+                    .as_ref()
+                    .is_none_or(|fg| palette.to_hex(cell.fg).eq_ignore_ascii_case(fg));
+                let bg_matches = wait
+                    .bg
+                    .as_ref()
+                    .is_none_or(|bg| palette.to_hex(cell.bg).eq_ignore_ascii_case(bg));
+                fg_matches && bg_matches
+            })
+        })
+    })
+}
+
+fn region_text(grid: &Grid<Cell>, wait: &WaitFor) -> Vec<String> {
+    let (lines, columns) = region(grid, wait);
+    lines
+        .map(|line| LineText::new(grid, line, columns.clone()).text)
+        .collect()
+}
+
+/// Lines and columns of the screen searched by `wait`, clamped to the screen size.
+fn region(grid: &Grid<Cell>, wait: &WaitFor) -> (Range<usize>, Range<usize>) {
+    let clamp = |range: Option<[usize; 2]>, len: usize| match range {
+        Some([start, end]) => start.min(len)..end.min(len),
+        None => 0..len,
+    };
+    (
+        clamp(wait.lines, grid.screen_lines()),
+        clamp(wait.columns, grid.columns()),
+    )
+}
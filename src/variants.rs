@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::{config::Background, nvim::NormalColors};
+
+/// Colors of a colorscheme loaded with one of the backgrounds of the config.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Variant {
+    pub(crate) background: Background,
+    pub(crate) normal: NormalColors,
+    /// Whether Normal's colors differ from the ones the colorscheme has with the first background,
+    /// i.e. whether the colorscheme actually has a variant for this background.
+    pub(crate) changes_normal: bool,
+}
+
+impl Variant {
+    /// Describes the colorscheme loaded with `background`, compared with the variant of the first
+    /// background if it's not that one.
+    pub(crate) fn new(
+        background: Background,
+        normal: NormalColors,
+        first: Option<&Variant>,
+    ) -> Variant {
+        // Colorschemes that ignore `background` still have it set to the one they're loaded with.
+        let changes_normal = first
+            .is_some_and(|first| (&first.normal.fg, &first.normal.bg) != (&normal.fg, &normal.bg));
+        Variant {
+            background,
+            normal,
+            changes_normal,
+        }
+    }
+}
+
+/// Writes `variants.json` to `out_dir`.
+pub(crate) fn export(out_dir: &Path, variants: &[Variant]) {
+    std::fs::create_dir_all(out_dir).unwrap();
+    let file = std::fs::File::create(out_dir.join("variants.json")).unwrap();
+    serde_json::to_writer_pretty(file, variants).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Variant;
+    use crate::{config::Background, nvim::NormalColors};
+
+    fn normal(fg: &str, bg: &str, background: &str) -> NormalColors {
+        NormalColors {
+            fg: Some(fg.to_string()),
+            bg: Some(bg.to_string()),
+            background: background.to_string(),
+        }
+    }
+
+    #[test]
+    fn first_variant_changes_nothing() {
+        let dark = Variant::new(Background::Dark, normal("#c0caf5", "#1a1b26", "dark"), None);
+        assert!(!dark.changes_normal);
+    }
+
+    #[test]
+    fn variant_with_other_colors_changes_normal() {
+        let dark = Variant::new(Background::Dark, normal("#c0caf5", "#1a1b26", "dark"), None);
+        let light = Variant::new(
+            Background::Light,
+            normal("#3760bf", "#e1e2e7", "light"),
+            Some(&dark),
+        );
+        assert!(light.changes_normal);
+    }
+
+    #[test]
+    fn variant_with_only_another_background_option_keeps_normal() {
+        let dark = Variant::new(Background::Dark, normal("#c0caf5", "#1a1b26", "dark"), None);
+        let light = Variant::new(
+            Background::Light,
+            normal("#c0caf5", "#1a1b26", "light"),
+            Some(&dark),
+        );
+        assert!(!light.changes_normal);
+    }
+}
@@ -2,11 +2,16 @@ use alacritty_terminal::{
     ansi::Processor, config::Config, event::VoidListener, term::test::TermSize, Term,
 };
 
-pub(crate) fn term() -> Term<VoidListener> {
+use crate::config::Size;
+
+pub(crate) fn term(size: Size) -> Term<VoidListener> {
     let config = Config::default();
-    let term_size = TermSize::new(super::COLUMNS, super::LINES);
 
-    Term::new(&config, &term_size, VoidListener)
+    Term::new(&config, &term_size(size), VoidListener)
+}
+
+pub(crate) fn term_size(size: Size) -> TermSize {
+    TermSize::new(size.columns, size.lines)
 }
 
 pub(crate) fn processor() -> Processor {